@@ -0,0 +1,603 @@
+//! Asset bookkeeping: finding references to attachments and collecting unused files.
+
+use crate::links;
+use crate::markdown::{self, LineKind};
+use crate::templates::TEMPLATES_DIR;
+use crate::NoteLayout;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Folder (relative to the notes root) that pasted and dropped files are stored in.
+pub const ASSETS_DIR: &str = "assets";
+
+/// Folder (relative to the notes root) that cleaned-up files are moved to.
+pub const TRASH_DIR: &str = ".verso/trash";
+
+static HTML_SRC_RE: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(
+        r#"(?i)<(?:img|source|video|audio|embed|iframe|object|a)\b[^>]*?\b(?:src|href|data)\s*=\s*(?:"([^"]*)"|'([^']*)')"#,
+    )
+    .unwrap()
+});
+static REFERENCE_DEF_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"^ {0,3}\[[^\]]+\]:\s*<?([^\s>]+)>?").unwrap());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetFile {
+    /// Path relative to the notes root, POSIX separators (e.g. `assets/screenshot-1.png`)
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetCleanupReport {
+    pub dry_run: bool,
+    pub assets: Vec<AssetFile>,
+    pub total_size: u64,
+    /// Folder the files were moved to (relative to the notes root), if any were moved
+    pub trash_dir: Option<String>,
+}
+
+// Assets with identical content; `keep` is the copy references are merged into
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub keep: String,
+    pub duplicates: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetsReport {
+    pub groups: Vec<DuplicateGroup>,
+    /// Bytes used by the redundant copies
+    pub wasted_size: u64,
+    pub merged: bool,
+    /// Markdown files (relative to the notes root) whose references were rewritten
+    pub rewritten_files: Vec<String>,
+    /// Redundant copies left in place because a reference to them couldn't be rewritten
+    pub kept_duplicates: Vec<String>,
+    /// Notes that couldn't be read or written while merging
+    pub errors: Vec<String>,
+    /// Folder the redundant copies were moved to (relative to the notes root)
+    pub trash_dir: Option<String>,
+}
+
+/// SHA-256 of `data` as lowercase hex.
+pub fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// SHA-256 of a file's contents as lowercase hex.
+pub fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// An existing asset with exactly the same bytes as `data`, if any.
+/// Only files of the same size are hashed.
+pub fn find_identical(notes_root: &Path, layout: &NoteLayout, data: &[u8]) -> Option<String> {
+    find_same_content(notes_root, layout, data.len() as u64, || Some(content_hash(data)))
+}
+
+/// Like `find_identical`, for a file of `size` bytes outside the folder. The
+/// file is streamed through the hash rather than read into memory.
+pub fn find_identical_file(notes_root: &Path, layout: &NoteLayout, path: &Path, size: u64) -> Option<String> {
+    find_same_content(notes_root, layout, size, || hash_file(path).ok())
+}
+
+/// An asset of `size` bytes whose hash equals `hash()`, which is only
+/// computed when some asset has that size.
+fn find_same_content(
+    notes_root: &Path,
+    layout: &NoteLayout,
+    size: u64,
+    hash: impl FnOnce() -> Option<String>,
+) -> Option<String> {
+    let candidates: Vec<AssetFile> = list_assets(notes_root, layout)
+        .into_iter()
+        .filter(|asset| asset.size == size)
+        .collect();
+    if candidates.is_empty() {
+        return None;
+    }
+    let expected = hash()?;
+    candidates
+        .into_iter()
+        .find(|asset| hash_file(&notes_root.join(&asset.path)).ok().as_ref() == Some(&expected))
+        .map(|asset| asset.path)
+}
+
+/// Groups of assets with identical content. Within a group the shortest path
+/// (then alphabetical) is kept, so `shot.png` wins over `shot-1.png`.
+pub fn find_duplicate_groups(notes_root: &Path, layout: &NoteLayout) -> Vec<DuplicateGroup> {
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    for asset in list_assets(notes_root, layout) {
+        by_size.entry(asset.size).or_default().push(asset.path);
+    }
+
+    let mut groups = Vec::new();
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for path in paths {
+            if let Ok(hash) = hash_file(&notes_root.join(&path)) {
+                by_hash.entry(hash).or_default().push(path);
+            }
+        }
+        for (hash, mut paths) in by_hash {
+            if paths.len() < 2 {
+                continue;
+            }
+            paths.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+            let keep = paths.remove(0);
+            groups.push(DuplicateGroup {
+                hash,
+                size,
+                keep,
+                duplicates: paths,
+            });
+        }
+    }
+
+    groups.sort_by(|a, b| a.keep.cmp(&b.keep));
+    groups
+}
+
+/// What `merge_duplicates` changed.
+#[derive(Debug, Default)]
+pub struct MergeOutcome {
+    /// Markdown files (relative to the notes root) whose references were rewritten
+    pub rewritten_files: Vec<String>,
+    /// Duplicates left in place because a reference to them couldn't be rewritten
+    pub kept_duplicates: Vec<String>,
+    /// Notes that couldn't be read or written, with the reason
+    pub errors: Vec<String>,
+    /// Folder the merged duplicates were moved to (relative to the notes root)
+    pub trash_dir: Option<String>,
+}
+
+/// Point every reference to a duplicate at its group's kept copy, then move the
+/// duplicates to the trash. A duplicate is only trashed once every reference to
+/// it was rewritten: ambiguous references, failed writes and unreadable notes
+/// leave it in place. Failures are collected rather than stopping the merge.
+pub fn merge_duplicates(
+    notes_root: &Path,
+    layout: &NoteLayout,
+    groups: &[DuplicateGroup],
+) -> std::io::Result<MergeOutcome> {
+    let replacements: HashMap<&str, &str> = groups
+        .iter()
+        .flat_map(|g| g.duplicates.iter().map(move |d| (d.as_str(), g.keep.as_str())))
+        .collect();
+    let mut outcome = MergeOutcome::default();
+    if replacements.is_empty() {
+        return Ok(outcome);
+    }
+
+    let assets = list_assets(notes_root, layout);
+    let lookup = AssetLookup::new(&assets);
+    // Duplicates that are still referenced after the rewrite
+    let mut still_used: HashSet<&str> = HashSet::new();
+    let mut unreadable = false;
+
+    for (source_rel, file_path) in list_markdown_files(notes_root, layout) {
+        let content = match crate::encoding::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                // Its references are unknown, so no duplicate is safe to remove
+                outcome.errors.push(format!("Failed to read {}: {}", source_rel, e));
+                unreadable = true;
+                continue;
+            }
+        };
+        let base = reference_base(&source_rel);
+        let mut edits = Vec::new();
+        let mut rewritten_refs = Vec::new();
+        for file_ref in file_refs(&content) {
+            let matched = lookup.matches(notes_root, base, &file_ref);
+            let keep: HashSet<&str> = matched.iter().filter_map(|m| replacements.get(m).copied()).collect();
+            // Only rewrite unambiguous references to a duplicate
+            if let ([duplicate], [keep]) = (matched.as_slice(), keep.into_iter().collect::<Vec<_>>().as_slice()) {
+                let raw = &content[file_ref.span.0..file_ref.span.1];
+                let replacement = rewrite_ref(notes_root, base, &file_ref, raw, keep, &lookup);
+                edits.push((file_ref.span.0, file_ref.span.1, replacement));
+                rewritten_refs.push(*duplicate);
+            } else {
+                still_used.extend(matched.iter().filter(|m| replacements.contains_key(*m)));
+            }
+        }
+        if edits.is_empty() {
+            continue;
+        }
+        let updated = markdown::apply_edits(&content, edits);
+        match crate::write_note_atomic(&file_path, &updated) {
+            Ok(()) => outcome.rewritten_files.push(source_rel),
+            Err(e) => {
+                outcome.errors.push(format!("Failed to write {}: {}", source_rel, e));
+                still_used.extend(rewritten_refs);
+            }
+        }
+    }
+
+    let (duplicates, kept): (Vec<AssetFile>, Vec<AssetFile>) = assets
+        .iter()
+        .filter(|a| replacements.contains_key(a.path.as_str()))
+        .cloned()
+        .partition(|a| !unreadable && !still_used.contains(a.path.as_str()));
+    outcome.kept_duplicates = kept.into_iter().map(|a| a.path).collect();
+    if !duplicates.is_empty() {
+        outcome.trash_dir = Some(move_to_trash(notes_root, &duplicates)?);
+    }
+
+    Ok(outcome)
+}
+
+/// Format a reference to `new_path` in the same style as the original `raw` target.
+fn rewrite_ref(
+    notes_root: &Path,
+    source_rel: &str,
+    file_ref: &FileRef,
+    raw: &str,
+    new_path: &str,
+    lookup: &AssetLookup<'_>,
+) -> String {
+    let absolute = notes_root.join(new_path);
+    for prefix in ["asset://localhost/", "http://asset.localhost/", "https://asset.localhost/"] {
+        if raw.starts_with(prefix) {
+            return format!("{}{}", prefix, urlencoding::encode(&absolute.to_string_lossy()));
+        }
+    }
+    if raw.starts_with("file://") {
+        if let Ok(url) = url::Url::from_file_path(&absolute) {
+            return url.to_string();
+        }
+    }
+
+    let file_name = new_path.rsplit('/').next().unwrap_or(new_path);
+    if file_ref.wiki_syntax {
+        let unique = lookup
+            .by_name
+            .get(&file_name.to_lowercase())
+            .is_none_or(|paths| paths.len() == 1);
+        return if unique && !raw.contains('/') {
+            file_name.to_string()
+        } else {
+            new_path.to_string()
+        };
+    }
+
+    let path = if raw.starts_with('/') {
+        format!("/{}", new_path)
+    } else {
+        let source_dir = source_rel.rfind('/').map(|pos| &source_rel[..pos]).unwrap_or("");
+        links::relative_path(source_dir, new_path)
+    };
+    if raw != file_ref.target || path.contains(' ') {
+        path.split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect::<Vec<_>>()
+            .join("/")
+    } else {
+        path
+    }
+}
+
+/// All attachment files (hidden files skipped), sorted by path: everything under
+/// the attachment folders, plus the non-note files next to the notes (in the
+/// root and its note subfolders) when attachments are stored beside notes.
+pub fn list_assets(notes_root: &Path, layout: &NoteLayout) -> Vec<AssetFile> {
+    let mut assets = attachment_folder_assets(notes_root, layout);
+    if layout.attachments().stores_beside_notes() {
+        let loose = walkdir::WalkDir::new(notes_root)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || (not_hidden(e) && crate::is_visible_notes_entry(e, layout)))
+            .flatten()
+            .filter(|e| e.file_type().is_file() && !layout.is_note_file(e.path()));
+        assets.extend(loose.filter_map(|e| asset_file(notes_root, &e)));
+        assets.sort_by(|a, b| a.path.cmp(&b.path));
+        assets.dedup_by(|a, b| a.path == b.path);
+    }
+    assets
+}
+
+/// Files under the attachment folders only, sorted by path.
+fn attachment_folder_assets(notes_root: &Path, layout: &NoteLayout) -> Vec<AssetFile> {
+    let mut assets: Vec<AssetFile> = layout
+        .attachments()
+        .scan_dirs()
+        .iter()
+        .flat_map(|dir| {
+            walkdir::WalkDir::new(notes_root.join(dir))
+                .into_iter()
+                .filter_entry(|e| e.depth() == 0 || not_hidden(e))
+                .flatten()
+        })
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| asset_file(notes_root, &e))
+        .collect();
+    assets.sort_by(|a, b| a.path.cmp(&b.path));
+    assets.dedup_by(|a, b| a.path == b.path);
+    assets
+}
+
+fn not_hidden(entry: &walkdir::DirEntry) -> bool {
+    !entry.file_name().to_string_lossy().starts_with('.')
+}
+
+fn asset_file(notes_root: &Path, entry: &walkdir::DirEntry) -> Option<AssetFile> {
+    Some(AssetFile {
+        path: relative_posix(notes_root, entry.path())?,
+        size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+/// All markdown files in the folder, recursively, as (path relative to the
+/// root, absolute path). Dot-directories are skipped, except the templates
+/// folder: templates reference assets just like notes do.
+pub fn list_markdown_files(notes_root: &Path, layout: &NoteLayout) -> Vec<(String, PathBuf)> {
+    [notes_root.to_path_buf(), notes_root.join(TEMPLATES_DIR)]
+        .iter()
+        .flat_map(|dir| {
+            walkdir::WalkDir::new(dir)
+                .into_iter()
+                .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+                .flatten()
+        })
+        .filter(|e| e.file_type().is_file() && layout.is_note_file(e.path()))
+        .filter_map(|e| Some((relative_posix(notes_root, e.path())?, e.path().to_path_buf())))
+        .collect()
+}
+
+/// The markdown file references in `source_rel` are resolved from. Templates
+/// are expanded into new notes in the root, so their references are written
+/// as if they were there.
+fn reference_base(source_rel: &str) -> &str {
+    match source_rel.strip_prefix(TEMPLATES_DIR).and_then(|rest| rest.strip_prefix('/')) {
+        Some(template) => template.rsplit('/').next().unwrap_or(template),
+        None => source_rel,
+    }
+}
+
+fn relative_posix(root: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?;
+    Some(
+        rel.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// A reference to a file found in a markdown document.
+#[derive(Debug, Clone)]
+pub struct FileRef {
+    /// Percent-decoded target
+    pub target: String,
+    pub wiki_syntax: bool,
+    /// Byte range of the raw target in the document
+    pub span: (usize, usize),
+}
+
+/// Every link-like target in a markdown document: markdown and wiki links,
+/// reference definitions and HTML `src`/`href` attributes. Code is skipped.
+pub fn file_refs(content: &str) -> Vec<FileRef> {
+    let mut refs: Vec<FileRef> = links::extract_links(content)
+        .into_iter()
+        .map(|link| FileRef {
+            target: link.target,
+            wiki_syntax: link.wiki_syntax,
+            span: link.target_span,
+        })
+        .collect();
+
+    let decode = |raw: &str| {
+        urlencoding::decode(raw)
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| raw.to_string())
+    };
+    for line in markdown::lines(content) {
+        if line.kind != LineKind::Text {
+            continue;
+        }
+        let masked = markdown::mask_inline_code(line.text);
+        let html_values = HTML_SRC_RE
+            .captures_iter(&masked)
+            .filter_map(|caps| caps.get(1).or_else(|| caps.get(2)));
+        let reference_values = REFERENCE_DEF_RE.captures(&masked).and_then(|caps| caps.get(1));
+        for value in html_values.chain(reference_values) {
+            let raw = value.as_str().split(['#', '?']).next().unwrap_or("");
+            let start = line.offset + value.start();
+            refs.push(FileRef {
+                target: decode(raw),
+                wiki_syntax: false,
+                span: (start, start + raw.len()),
+            });
+        }
+    }
+
+    refs
+}
+
+/// File names of the local, non-note files a markdown document links to or
+/// embeds (e.g. `report.pdf`), for search indexing.
+pub fn attachment_names(content: &str, layout: &NoteLayout) -> Vec<String> {
+    let mut names: Vec<String> = file_refs(content)
+        .into_iter()
+        .filter(|r| r.wiki_syntax || !links::is_external_target(&r.target) || is_local_file_url(&r.target))
+        .filter_map(|r| {
+            let name = r.target.rsplit(['/', '\\']).next()?.to_string();
+            let is_file = Path::new(&name).extension().is_some() && !layout.is_note_file(Path::new(&name));
+            is_file.then_some(name)
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// URLs the editor uses for files on disk (`asset://`, `http://asset.localhost`, `file://`).
+fn is_local_file_url(target: &str) -> bool {
+    ["asset://", "http://asset.localhost/", "https://asset.localhost/", "file://"]
+        .iter()
+        .any(|prefix| target.starts_with(prefix))
+}
+
+/// Map a (decoded) link target written in the markdown file `source_rel` to a
+/// path relative to the notes root. Understands relative paths, root-relative
+/// paths, and the `asset://` / `http://asset.localhost` / `file://` URLs the
+/// editor produces for absolute paths.
+pub fn resolve_local_target(notes_root: &Path, source_rel: &str, target: &str) -> Option<String> {
+    let target = target.trim();
+    if target.is_empty() {
+        return None;
+    }
+
+    let absolute = if let Some(rest) = target
+        .strip_prefix("asset://localhost/")
+        .or_else(|| target.strip_prefix("http://asset.localhost/"))
+        .or_else(|| target.strip_prefix("https://asset.localhost/"))
+    {
+        let decoded = urlencoding::decode(rest)
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| rest.to_string());
+        Some(PathBuf::from(decoded))
+    } else if target.starts_with("file://") {
+        url::Url::parse(target).ok()?.to_file_path().ok()
+    } else if links::is_external_target(target) {
+        return None;
+    } else {
+        None
+    };
+
+    if let Some(abs) = absolute {
+        return relative_posix(notes_root, &abs);
+    }
+
+    let source_dir = source_rel.rfind('/').map(|pos| &source_rel[..pos]).unwrap_or("");
+    links::normalize_relative_path(source_dir, target)
+}
+
+/// Matches file references against a set of assets.
+/// Matching is case-insensitive so case-insensitive file systems never lose files.
+pub struct AssetLookup<'a> {
+    by_lower: HashMap<String, &'a str>,
+    by_name: HashMap<String, Vec<&'a str>>,
+}
+
+impl<'a> AssetLookup<'a> {
+    pub fn new(assets: &'a [AssetFile]) -> Self {
+        let mut by_lower = HashMap::new();
+        let mut by_name: HashMap<String, Vec<&str>> = HashMap::new();
+        for asset in assets {
+            by_lower.insert(asset.path.to_lowercase(), asset.path.as_str());
+            let name = asset.path.rsplit('/').next().unwrap_or(&asset.path).to_lowercase();
+            by_name.entry(name).or_default().push(asset.path.as_str());
+        }
+        Self { by_lower, by_name }
+    }
+
+    /// Assets a reference written in the markdown file `source_rel` points at.
+    pub fn matches(&self, notes_root: &Path, source_rel: &str, file_ref: &FileRef) -> Vec<&'a str> {
+        let candidates = [
+            resolve_local_target(notes_root, source_rel, &file_ref.target),
+            // Root-relative fallback for links written from the root's perspective
+            links::normalize_relative_path("", &file_ref.target),
+        ];
+        let mut found: Vec<&str> = candidates
+            .into_iter()
+            .flatten()
+            .filter_map(|rel| self.by_lower.get(&rel.to_lowercase()).copied())
+            .collect();
+        // Wiki embeds (![[image.png]]) match by file name anywhere
+        if found.is_empty() && file_ref.wiki_syntax {
+            let name = file_ref.target.rsplit('/').next().unwrap_or(&file_ref.target);
+            found.extend(self.by_name.get(&name.to_lowercase()).into_iter().flatten());
+        }
+        found.dedup();
+        found
+    }
+}
+
+/// Paths (relative to the notes root) of assets referenced from any markdown
+/// file, and the markdown files that couldn't be read, with the reason.
+pub fn referenced_assets(
+    notes_root: &Path,
+    layout: &NoteLayout,
+    assets: &[AssetFile],
+) -> (HashSet<String>, Vec<String>) {
+    let lookup = AssetLookup::new(assets);
+    let mut referenced = HashSet::new();
+    let mut unreadable = Vec::new();
+    for (source_rel, file_path) in list_markdown_files(notes_root, layout) {
+        let content = match crate::encoding::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                unreadable.push(format!("{}: {}", source_rel, e));
+                continue;
+            }
+        };
+        for file_ref in file_refs(&content) {
+            for path in lookup.matches(notes_root, reference_base(&source_rel), &file_ref) {
+                referenced.insert(path.to_string());
+            }
+        }
+    }
+    (referenced, unreadable)
+}
+
+/// Files in the attachment folders not referenced by any markdown file in the
+/// folder. Loose files next to the notes are never reported: nothing tells an
+/// unreferenced attachment apart from the user's own PDFs and text files there.
+/// Fails if any markdown file can't be read, since the assets only it uses
+/// would look unused.
+pub fn find_unused(notes_root: &Path, layout: &NoteLayout) -> Result<Vec<AssetFile>, String> {
+    let (referenced, unreadable) = referenced_assets(notes_root, layout, &list_assets(notes_root, layout));
+    if !unreadable.is_empty() {
+        return Err(format!(
+            "Can't tell which assets are unused, some notes couldn't be read: {}",
+            unreadable.join("; ")
+        ));
+    }
+    Ok(attachment_folder_assets(notes_root, layout)
+        .into_iter()
+        .filter(|a| !referenced.contains(&a.path))
+        .collect())
+}
+
+/// Move the given assets into a timestamped folder under `.verso/trash`,
+/// keeping their relative paths. Returns the trash folder (relative to the root).
+pub fn move_to_trash(notes_root: &Path, assets: &[AssetFile]) -> std::io::Result<String> {
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut trash_rel = format!("{}/{}", TRASH_DIR, timestamp);
+    let mut counter = 1;
+    while notes_root.join(&trash_rel).exists() {
+        trash_rel = format!("{}/{}-{}", TRASH_DIR, timestamp, counter);
+        counter += 1;
+    }
+    let trash_dir = notes_root.join(&trash_rel);
+
+    for asset in assets {
+        let source = notes_root.join(&asset.path);
+        let target = trash_dir.join(&asset.path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if std::fs::rename(&source, &target).is_err() {
+            // Cross-device moves (e.g. a symlinked assets folder) need copy + delete
+            std::fs::copy(&source, &target)?;
+            std::fs::remove_file(&source)?;
+        }
+    }
+
+    Ok(trash_rel)
+}
@@ -0,0 +1,272 @@
+//! Where imported attachments are stored and how their files are named.
+
+use crate::assets::ASSETS_DIR;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttachmentLocation {
+    /// Shared `assets/` folder at the root of the notes folder
+    #[default]
+    Vault,
+    /// `assets/<note>/`, one subfolder per note
+    NoteSubfolder,
+    /// Next to the note itself
+    NoteFolder,
+    /// A custom folder relative to the notes root
+    Custom,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentSettings {
+    #[serde(default)]
+    pub location: AttachmentLocation,
+    /// Folder used with the `custom` location, e.g. `attachments` or `media/images`
+    pub custom_path: Option<String>,
+    /// File name template without extension, e.g. `{{title}}-{{date}}-{{counter}}`.
+    /// Tokens: `{{date}}`, `{{time}}`, `{{timestamp}}`, `{{title}}`, `{{name}}`, `{{counter}}`.
+    pub name_template: Option<String>,
+}
+
+// A file copied into the notes folder by import_attachment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedAttachment {
+    /// Path relative to the notes root, POSIX separators
+    pub path: String,
+    pub mime_type: String,
+    pub size: u64,
+    /// Whether the editor can show the file inline (images, audio, video)
+    pub inline: bool,
+}
+
+/// (extension, MIME type) for common attachment types.
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("heic", "image/heic"),
+    ("heif", "image/heif"),
+    ("bmp", "image/bmp"),
+    ("ico", "image/x-icon"),
+    ("avif", "image/avif"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("mp3", "audio/mpeg"),
+    ("m4a", "audio/mp4"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("oga", "audio/ogg"),
+    ("flac", "audio/flac"),
+    ("mp4", "video/mp4"),
+    ("m4v", "video/mp4"),
+    ("mov", "video/quicktime"),
+    ("webm", "video/webm"),
+    ("ogv", "video/ogg"),
+    ("pdf", "application/pdf"),
+    ("csv", "text/csv"),
+    ("tsv", "text/tab-separated-values"),
+    ("txt", "text/plain"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("7z", "application/x-7z-compressed"),
+    ("rar", "application/vnd.rar"),
+    ("doc", "application/msword"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("xls", "application/vnd.ms-excel"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("ppt", "application/vnd.ms-powerpoint"),
+    ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+];
+
+/// MIME type for a file extension, `application/octet-stream` if unknown.
+pub fn mime_type_for_extension(extension: &str) -> &'static str {
+    let extension = extension.to_ascii_lowercase();
+    MIME_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| *mime)
+        .unwrap_or("application/octet-stream")
+}
+
+/// Whether a file of this MIME type can be shown inline in the editor.
+/// HEIC/HEIF and TIFF are left out as the webviews can't render them everywhere.
+pub fn is_inline_renderable(mime_type: &str) -> bool {
+    match mime_type {
+        "image/heic" | "image/heif" | "image/tiff" => false,
+        _ => ["image/", "audio/", "video/"].iter().any(|p| mime_type.starts_with(p)),
+    }
+}
+
+/// Human-readable file size, e.g. `12.5 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Values available to the file name template.
+pub struct NameContext<'a> {
+    /// Title of the note the file is attached to
+    pub title: Option<&'a str>,
+    /// Original file name without extension (`screenshot` for pasted images)
+    pub name: &'a str,
+}
+
+impl AttachmentSettings {
+    /// Check the custom folder, returning a user-facing error if it is unusable.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.location == AttachmentLocation::Custom {
+            self.custom_dir()?;
+        }
+        Ok(())
+    }
+
+    /// The custom folder as a clean relative POSIX path.
+    fn custom_dir(&self) -> Result<String, String> {
+        let raw = self.custom_path.as_deref().unwrap_or("").replace('\\', "/");
+        let segments: Vec<&str> = raw
+            .split('/')
+            .map(str::trim)
+            .filter(|s| !s.is_empty() && *s != ".")
+            .collect();
+        if segments.is_empty() {
+            return Err("Custom attachment folder is empty".to_string());
+        }
+        if raw.starts_with('/') || Path::new(&raw).is_absolute() {
+            return Err("Custom attachment folder must be relative to the notes folder".to_string());
+        }
+        if segments.iter().any(|s| *s == ".." || s.starts_with('.')) {
+            return Err(format!("Invalid custom attachment folder: {}", raw));
+        }
+        Ok(segments.join("/"))
+    }
+
+    /// Folder (relative to the notes root, POSIX separators, empty for the root)
+    /// that a file attached to `note_id` is stored in.
+    pub fn target_dir(&self, note_id: Option<&str>) -> Result<String, String> {
+        match self.location {
+            AttachmentLocation::Vault => Ok(ASSETS_DIR.to_string()),
+            AttachmentLocation::NoteSubfolder => Ok(match note_id {
+                Some(id) => format!(
+                    "{}/{}",
+                    ASSETS_DIR,
+                    id.split('/').map(crate::sanitize_filename).collect::<Vec<_>>().join("/")
+                ),
+                None => ASSETS_DIR.to_string(),
+            }),
+            AttachmentLocation::NoteFolder => Ok(note_id
+                .and_then(|id| id.rfind('/').map(|pos| id[..pos].to_string()))
+                .unwrap_or_default()),
+            AttachmentLocation::Custom => self.custom_dir(),
+        }
+    }
+
+    /// Folders (relative to the notes root, POSIX separators) that hold
+    /// attachments and are never note folders.
+    pub fn hidden_dirs(&self) -> Vec<String> {
+        let mut dirs = vec![ASSETS_DIR.to_string()];
+        if self.location == AttachmentLocation::Custom {
+            if let Ok(custom) = self.custom_dir() {
+                if !dirs.contains(&custom) {
+                    dirs.push(custom);
+                }
+            }
+        }
+        dirs
+    }
+
+    /// Folders (relative to the notes root) scanned for attachments. Files
+    /// imported under an earlier setting stay in `assets/`, so it is always included.
+    pub fn scan_dirs(&self) -> Vec<String> {
+        let mut dirs = vec![ASSETS_DIR.to_string()];
+        if self.location == AttachmentLocation::Custom {
+            if let Ok(custom) = self.custom_dir() {
+                if !dirs.iter().any(|d| custom == *d || custom.starts_with(&format!("{}/", d))) {
+                    dirs.push(custom);
+                }
+            }
+        }
+        dirs
+    }
+
+    /// Whether attachments are stored loose next to the notes.
+    pub fn stores_beside_notes(&self) -> bool {
+        self.location == AttachmentLocation::NoteFolder
+    }
+
+    /// Pick a file name (with `extension`) that doesn't exist yet in `dir`.
+    /// Without a template this is `screenshot-<unix>` / the original name, with
+    /// `-1`, `-2`, ... appended on collision; a `{{counter}}` token counts from 1.
+    pub fn unique_file_name(&self, dir: &Path, context: &NameContext<'_>, extension: &str) -> String {
+        let now = chrono::Local::now();
+        let default_template = if context.name == "screenshot" {
+            "{{name}}-{{timestamp}}"
+        } else {
+            "{{name}}"
+        };
+        let template = self
+            .name_template
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .unwrap_or(default_template);
+
+        let expand = |counter: Option<usize>| {
+            let expanded = template
+                .replace("{{date}}", &now.format("%Y-%m-%d").to_string())
+                .replace("{{time}}", &now.format("%H%M%S").to_string())
+                .replace("{{timestamp}}", &now.timestamp().to_string())
+                .replace("{{title}}", context.title.unwrap_or("Untitled"))
+                .replace("{{name}}", context.name)
+                .replace("{{counter}}", &counter.map(|c| c.to_string()).unwrap_or_default());
+            crate::sanitize_filename(&expanded)
+        };
+        let with_extension = |stem: &str| {
+            if extension.is_empty() {
+                stem.to_string()
+            } else {
+                format!("{}.{}", stem, extension)
+            }
+        };
+
+        if template.contains("{{counter}}") {
+            let mut counter = 1;
+            loop {
+                let name = with_extension(&expand(Some(counter)));
+                if !dir.join(&name).exists() {
+                    return name;
+                }
+                counter += 1;
+            }
+        }
+
+        let stem = expand(None);
+        let mut name = with_extension(&stem);
+        let mut counter = 1;
+        while dir.join(&name).exists() {
+            name = with_extension(&format!("{}-{}", stem, counter));
+            counter += 1;
+        }
+        name
+    }
+}
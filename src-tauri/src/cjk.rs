@@ -0,0 +1,177 @@
+//! Helpers for Chinese, Japanese and Korean text, including the CJK-Latin
+//! spacing formatter.
+
+use crate::{markdown, tags};
+use pulldown_cmark::{Event, LinkType, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::sync::LazyLock;
+
+/// Whether `c` is a CJK ideograph, kana or CJK punctuation/symbol. Uses the
+/// same ranges as the editor's pangu spacing, plus the supplementary
+/// ideograph planes. Hangul syllables are left out because Korean separates
+/// words with spaces.
+pub fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2E80}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' | '\u{FE30}'..='\u{FE4F}' | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+/// What spacing changes (or would change) in one note.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpacingChange {
+    pub id: String,
+    /// Number of spaces added
+    pub insertions: usize,
+    /// 1-based lines that change
+    pub lines: Vec<usize>,
+}
+
+impl SpacingChange {
+    /// Summarize the insertions for a note, or None if there are none.
+    pub fn new(id: &str, content: &str, insertions: &[usize]) -> Option<Self> {
+        if insertions.is_empty() {
+            return None;
+        }
+        let mut lines: Vec<usize> = insertions
+            .iter()
+            .map(|&pos| content[..pos].matches('\n').count() + 1)
+            .collect();
+        lines.dedup();
+        Some(SpacingChange {
+            id: id.to_string(),
+            insertions: insertions.len(),
+            lines,
+        })
+    }
+}
+
+/// Bare URLs inside text, which spacing must not split.
+static URL_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(?i)(?:[a-z][a-z0-9+.-]*://|www\.)[^\s<>]+").unwrap());
+
+/// Whether a space belongs between `prev` and `next`: a CJK character next
+/// to an ASCII letter or digit, in either order.
+fn needs_space(prev: char, next: char) -> bool {
+    (is_cjk(prev) && next.is_ascii_alphanumeric()) || (prev.is_ascii_alphanumeric() && is_cjk(next))
+}
+
+/// Byte offsets in `content` where CJK-Latin spacing would insert a space.
+/// Only prose is touched: frontmatter, code spans and blocks, HTML, URLs,
+/// inline `#tags`, link targets and link text that doubles as a target (wikilinks,
+/// `[label][]` references, autolinks) are left alone.
+pub fn spacing_insertions(content: &str) -> Vec<usize> {
+    let mut insertions = Vec::new();
+    // Depth inside blocks and links whose text must stay as written
+    let mut skip = 0usize;
+    // Whether each open link is protected
+    let mut links: Vec<bool> = Vec::new();
+    // Contiguous run of text events, as a byte range
+    let mut run: Option<Range<usize>> = None;
+    // Tags are matched by name, so spacing inside one would make it a different tag
+    let tags = tags::inline_tag_spans(content);
+
+    let flush = |run: &mut Option<Range<usize>>, insertions: &mut Vec<usize>| {
+        if let Some(range) = run.take() {
+            span_insertions(content, range, &tags, insertions);
+        }
+    };
+
+    for (event, range) in Parser::new_ext(content, markdown::parser_options()).into_offset_iter() {
+        match event {
+            Event::Text(_) if skip == 0 => match run {
+                Some(ref mut open) if open.end == range.start => open.end = range.end,
+                _ => {
+                    flush(&mut run, &mut insertions);
+                    run = Some(range);
+                }
+            },
+            Event::Start(tag) => {
+                flush(&mut run, &mut insertions);
+                let protected = is_protected(&tag);
+                if matches!(tag, Tag::Link { .. }) {
+                    links.push(protected);
+                }
+                if protected {
+                    skip += 1;
+                }
+            }
+            Event::End(tag) => {
+                flush(&mut run, &mut insertions);
+                let protected = match tag {
+                    TagEnd::Link => links.pop().unwrap_or(false),
+                    TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_) => true,
+                    _ => false,
+                };
+                if protected {
+                    skip = skip.saturating_sub(1);
+                }
+            }
+            _ => flush(&mut run, &mut insertions),
+        }
+    }
+    flush(&mut run, &mut insertions);
+    insertions
+}
+
+/// Blocks and links whose text must not be changed.
+fn is_protected(tag: &Tag) -> bool {
+    match tag {
+        Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_) => true,
+        Tag::Link { link_type, .. } => !matches!(link_type, LinkType::Inline | LinkType::Reference),
+        _ => false,
+    }
+}
+
+/// Spacing insertions within one run of prose, skipping the insides of URLs
+/// and of the `tags` spans (absolute byte ranges in `content`).
+fn span_insertions(content: &str, range: Range<usize>, tags: &[(usize, usize)], insertions: &mut Vec<usize>) {
+    let text = &content[range.clone()];
+    let urls: Vec<Range<usize>> = URL_RE.find_iter(text).map(|m| m.range()).collect();
+    let mut prev: Option<char> = None;
+    for (pos, c) in text.char_indices() {
+        let at = range.start + pos;
+        let protected = urls.iter().any(|url| url.start < pos && pos < url.end)
+            || tags.iter().any(|&(start, end)| start < at && at < end);
+        if prev.is_some_and(|p| needs_space(p, c)) && !protected {
+            insertions.push(at);
+        }
+        prev = Some(c);
+    }
+}
+
+/// `content` with a space inserted at each offset (offsets ascending).
+pub fn insert_spaces(content: &str, insertions: &[usize]) -> String {
+    let mut result = String::with_capacity(content.len() + insertions.len());
+    let mut cursor = 0;
+    for &pos in insertions {
+        result.push_str(&content[cursor..pos]);
+        result.push(' ');
+        cursor = pos;
+    }
+    result.push_str(&content[cursor..]);
+    result
+}
+
+/// Add spaces between CJK and Latin text in a markdown document.
+pub fn format_spacing(content: &str) -> String {
+    insert_spaces(content, &spacing_insertions(content))
+}
+
+/// Columns `text` takes up in a monospace font: CJK, Hangul and fullwidth
+/// characters count as two.
+pub fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| {
+            let wide = is_cjk(c)
+                || matches!(c, '\u{1100}'..='\u{115F}' | '\u{AC00}'..='\u{D7A3}' | '\u{FF00}'..='\u{FF60}' | '\u{FFE0}'..='\u{FFE6}');
+            if wide {
+                2
+            } else {
+                1
+            }
+        })
+        .sum()
+}
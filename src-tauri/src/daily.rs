@@ -0,0 +1,108 @@
+//! Daily notes: one note per day, named from a date format. They live in the
+//! top level of the notes folder, like every other note, so they are listed,
+//! searched and linked like any other.
+
+use crate::NoteLayout;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Default file name format (chrono syntax).
+const DEFAULT_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyNoteSettings {
+    /// chrono format for the note name; it can't contain `/`
+    pub format: Option<String>,
+    /// Template ID (see `templates`) used for new daily notes
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyNote {
+    /// Day in `YYYY-MM-DD` form
+    pub date: String,
+    pub id: String,
+}
+
+impl DailyNoteSettings {
+    fn format(&self) -> &str {
+        self.format
+            .as_deref()
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .unwrap_or(DEFAULT_FORMAT)
+    }
+
+    /// Note ID of the daily note for `date`.
+    pub fn note_id(&self, date: NaiveDate) -> Result<String, String> {
+        use std::fmt::Write;
+        let mut name = String::new();
+        write!(name, "{}", date.format(self.format()))
+            .map_err(|_| format!("Invalid daily note format: {}", self.format()))?;
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(format!("Invalid daily note format: {}", self.format()));
+        }
+        if name.contains(['/', '\\']) {
+            return Err(format!(
+                "Daily note format can't contain folders, daily notes are kept with the other notes: {}",
+                self.format()
+            ));
+        }
+        Ok(name)
+    }
+
+    /// Check the format, returning a user-facing error if it is unusable.
+    pub fn validate(&self) -> Result<(), String> {
+        self.note_id(NaiveDate::default()).map(|_| ())
+    }
+
+    /// The day a note ID stands for, if it is a daily note.
+    pub fn date_of(&self, id: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(id, self.format()).ok()
+    }
+
+    /// Existing daily notes between `start` and `end` (inclusive, either open), oldest first.
+    pub fn list(
+        &self,
+        notes_root: &Path,
+        layout: &NoteLayout,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> Vec<DailyNote> {
+        let mut days: Vec<(NaiveDate, String)> = crate::list_note_files(notes_root, layout)
+            .into_iter()
+            .filter_map(|(id, _)| Some((self.date_of(&id)?, id)))
+            .filter(|(date, _)| start.is_none_or(|s| *date >= s) && end.is_none_or(|e| *date <= e))
+            .collect();
+        days.sort();
+        days.into_iter()
+            .map(|(date, id)| DailyNote {
+                date: date.format("%Y-%m-%d").to_string(),
+                id,
+            })
+            .collect()
+    }
+
+    /// Closest existing daily note before (or after) `date`, skipping missing days.
+    pub fn adjacent(
+        &self,
+        notes_root: &Path,
+        layout: &NoteLayout,
+        date: NaiveDate,
+        forward: bool,
+    ) -> Option<DailyNote> {
+        if forward {
+            self.list(notes_root, layout, date.succ_opt(), None).into_iter().next()
+        } else {
+            self.list(notes_root, layout, None, date.pred_opt()).pop()
+        }
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date argument.
+pub fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| format!("Invalid date: {}", date))
+}
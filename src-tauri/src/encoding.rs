@@ -0,0 +1,149 @@
+//! Character encoding detection for notes, so non-UTF-8 files can be read and
+//! written back in their original encoding.
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::path::Path;
+
+/// How many bytes are sampled when guessing BOM-less UTF-16.
+const UTF16_SAMPLE: usize = 4096;
+
+/// The encoding of a note file, including whether it starts with a BOM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEncoding {
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+}
+
+impl TextEncoding {
+    pub const UTF8: TextEncoding = TextEncoding {
+        encoding: UTF_8,
+        bom: false,
+    };
+
+    /// WHATWG name of the encoding (e.g. `UTF-8`, `GBK`, `Shift_JIS`, `UTF-16LE`).
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    /// Encode `text` in this encoding. Returns None if it contains characters
+    /// the encoding can't represent.
+    pub fn encode(&self, text: &str) -> Option<Vec<u8>> {
+        let mut out = Vec::with_capacity(text.len() + 3);
+        // encoding_rs only decodes UTF-16, so encode it by hand
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            let little_endian = self.encoding == UTF_16LE;
+            let units = if self.bom { Some(0xFEFF) } else { None }
+                .into_iter()
+                .chain(text.encode_utf16());
+            for unit in units {
+                out.extend(if little_endian {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                });
+            }
+            return Some(out);
+        }
+
+        if self.bom && self.encoding == UTF_8 {
+            out.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        let (bytes, _, had_unmappable) = self.encoding.encode(text);
+        if had_unmappable {
+            return None;
+        }
+        out.extend_from_slice(&bytes);
+        Some(out)
+    }
+}
+
+/// Detect the encoding of `bytes`: a BOM wins, then BOM-less UTF-16 (by its
+/// zero bytes, which are also valid UTF-8), then valid UTF-8, then a
+/// statistical guess.
+pub fn detect(bytes: &[u8]) -> TextEncoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return TextEncoding { encoding, bom: true };
+    }
+    if let Some(encoding) = guess_utf16(bytes) {
+        return TextEncoding { encoding, bom: false };
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return TextEncoding::UTF8;
+    }
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    TextEncoding {
+        encoding: detector.guess(None, true),
+        bom: false,
+    }
+}
+
+/// Mostly-ASCII UTF-16 text has a zero in every other byte.
+fn guess_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SAMPLE)];
+    if sample.len() < 2 || !sample.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Decode `bytes` using the detected encoding (the BOM is dropped). The flag
+/// is set when some bytes weren't valid in that encoding and were decoded as
+/// U+FFFD, so writing the text back would lose them.
+pub fn decode(bytes: &[u8]) -> (String, TextEncoding, bool) {
+    let detected = detect(bytes);
+    let (text, malformed) = if detected.bom {
+        detected.encoding.decode_with_bom_removal(bytes)
+    } else {
+        detected.encoding.decode_without_bom_handling(bytes)
+    };
+    (text.into_owned(), detected, malformed)
+}
+
+/// Read a note file in whatever encoding it uses.
+pub fn read_to_string(path: &Path) -> std::io::Result<String> {
+    std::fs::read(path).map(|bytes| decode(&bytes).0)
+}
+
+/// Encode `text` for writing over the file at `path`, keeping that file's
+/// encoding and BOM. New files, `to_utf8`, and text the old encoding can't
+/// represent are written as UTF-8 (keeping a UTF-8 BOM if there was one).
+/// Fails if the existing file doesn't decode cleanly, since its text was read
+/// with replacement characters and writing it would destroy the original bytes.
+pub fn encode_for_file(path: &Path, text: &str, to_utf8: bool) -> std::io::Result<(Vec<u8>, TextEncoding)> {
+    let existing = match std::fs::read(path) {
+        Ok(bytes) => {
+            let (_, detected, malformed) = decode(&bytes);
+            if malformed {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "{} isn't valid {} and would be damaged by saving, so it is read-only",
+                        path.display(),
+                        detected.name()
+                    ),
+                ));
+            }
+            detected
+        }
+        Err(_) => TextEncoding::UTF8,
+    };
+    let target = if to_utf8 { TextEncoding::UTF8 } else { existing };
+    if let Some(bytes) = target.encode(text) {
+        return Ok((bytes, target));
+    }
+    let fallback = TextEncoding {
+        encoding: UTF_8,
+        bom: existing.bom && existing.encoding == UTF_8,
+    };
+    Ok((fallback.encode(text).unwrap_or_else(|| text.as_bytes().to_vec()), fallback))
+}
@@ -0,0 +1,718 @@
+//! HTML export: renders notes to standalone pages, or a whole folder to a
+//! static site, styled with the app's theme colors.
+
+use crate::assets::{self, AssetFile, AssetLookup, FileRef};
+use crate::links::{self, Resolution, Resolver};
+use crate::{attachments, markdown, outline, preview, tags, NoteLayout, ThemeColors, ThemeSettings};
+use base64::Engine;
+use pulldown_cmark::{CowStr, Event, LinkType, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Site folder the note pages are written to, so they can't clash with the
+/// index, tag pages or copied assets.
+const NOTES_DIR: &str = "notes";
+
+/// Site folder of the tag pages.
+const TAGS_DIR: &str = "tags";
+
+/// File name of the site's search index. It's a script assigning the index to
+/// `window.searchIndex` rather than JSON, because pages opened from `file://`
+/// can't fetch files.
+const SEARCH_INDEX: &str = "search-index.js";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportTheme {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetMode {
+    /// Embed images in the page as data URIs
+    #[default]
+    Inline,
+    /// Copy referenced files next to the page
+    Copy,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteExportOptions {
+    /// Shown on the index page; defaults to the folder name
+    pub title: Option<String>,
+    /// Defaults to the app's theme mode (light when it follows the system)
+    pub theme: Option<ExportTheme>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteExportReport {
+    pub notes: usize,
+    pub tags: usize,
+    /// Copied files, relative to the notes root
+    pub assets: Vec<String>,
+    /// Notes left out because they couldn't be read, as "relative path: error"
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HtmlExportOptions {
+    /// HTML file to write
+    pub output_path: String,
+    /// Defaults to the app's theme mode (light when it follows the system)
+    pub theme: Option<ExportTheme>,
+    #[serde(default)]
+    pub assets: AssetMode,
+}
+
+/// What a URL in a note belongs to, for `render_note`'s rewrite callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefKind {
+    Link,
+    WikiLink,
+    Image,
+    WikiEmbed,
+}
+
+impl RefKind {
+    pub fn is_wiki(self) -> bool {
+        matches!(self, RefKind::WikiLink | RefKind::WikiEmbed)
+    }
+
+    pub fn is_image(self) -> bool {
+        matches!(self, RefKind::Image | RefKind::WikiEmbed)
+    }
+}
+
+/// Page styles, after the `:root` color variables.
+const STYLE: &str = r#"
+* { box-sizing: border-box; }
+body {
+  margin: 0;
+  background: var(--color-bg);
+  color: var(--color-text);
+  font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, "Helvetica Neue", Arial, sans-serif;
+  font-size: 17px;
+  line-height: 1.75;
+}
+main { max-width: 760px; margin: 0 auto; padding: 48px 24px; }
+nav { max-width: 760px; margin: 0 auto; padding: 16px 24px 0; font-size: 14px; color: var(--color-text-muted); }
+a { color: var(--color-accent); text-decoration: underline; text-decoration-color: var(--color-border); }
+a:hover { text-decoration-color: currentColor; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.3; margin: 1.6em 0 0.6em; }
+h1 { font-size: 30px; } h2 { font-size: 25px; } h3 { font-size: 21px; } h4 { font-size: 18px; } h5, h6 { font-size: 16px; }
+p, ul, ol, blockquote, pre, table { margin: 0 0 1.1em; }
+blockquote { padding-left: 1em; border-left: 3px solid var(--color-border); color: var(--color-text-muted); }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 0.9em; background: var(--color-bg-muted); border-radius: 4px; padding: 0.1em 0.3em; }
+pre { background: var(--color-bg-muted); border-radius: 6px; padding: 12px 16px; overflow-x: auto; }
+pre code { background: none; padding: 0; }
+table { border-collapse: collapse; display: block; overflow-x: auto; }
+th, td { border: 1px solid var(--color-border); padding: 6px 12px; }
+th { background: var(--color-bg-emphasis); }
+hr { border: none; border-top: 1px solid var(--color-border); margin: 2em 0; }
+img { max-width: 100%; }
+li > input[type="checkbox"] { margin: 0 0.4em 0 -1.4em; }
+li:has(> input[type="checkbox"]) { list-style: none; }
+.footnote-definition { font-size: 0.9em; color: var(--color-text-muted); }
+.footnote-definition p { display: inline; }
+mark { background: var(--color-bg-emphasis); color: inherit; }
+.note-list { list-style: none; padding: 0; }
+.note-list li { margin-bottom: 0.8em; }
+.muted { color: var(--color-text-muted); font-size: 0.9em; }
+input[type="search"] { width: 100%; font: inherit; color: inherit; background: var(--color-bg-muted); border: 1px solid var(--color-border); border-radius: 6px; padding: 6px 12px; margin-bottom: 1em; }
+"#;
+
+/// Stylesheet for `theme` (or the app's theme mode): the app's default colors
+/// with the user's custom colors for that mode on top.
+pub fn stylesheet(settings: &ThemeSettings, theme: Option<ExportTheme>) -> String {
+    let theme = theme.unwrap_or(if settings.mode == "dark" {
+        ExportTheme::Dark
+    } else {
+        ExportTheme::Light
+    });
+    let custom = match theme {
+        ExportTheme::Light => settings.custom_light_colors.clone(),
+        ExportTheme::Dark => settings.custom_dark_colors.clone(),
+    }
+    .unwrap_or_default();
+    let ThemeColors {
+        bg,
+        bg_secondary,
+        bg_muted,
+        bg_emphasis,
+        text,
+        text_muted,
+        text_inverse,
+        border,
+        accent,
+    } = custom;
+
+    // Same defaults as App.css
+    let colors = [
+        ("bg", bg, "#ffffff", "#000000"),
+        ("bg-secondary", bg_secondary, "#ffffff", "#000000"),
+        ("bg-muted", bg_muted, "rgba(0, 0, 0, 0.05)", "rgba(255, 255, 255, 0.06)"),
+        ("bg-emphasis", bg_emphasis, "rgba(0, 0, 0, 0.09)", "rgba(255, 255, 255, 0.1)"),
+        ("text", text, "#171717", "#e5e5e5"),
+        ("text-muted", text_muted, "#737373", "#737373"),
+        ("text-inverse", text_inverse, "#fafafa", "#0a0a0a"),
+        ("border", border, "rgba(0, 0, 0, 0.1)", "rgba(255, 255, 255, 0.1)"),
+        ("accent", accent, "#171717", "#e5e5e5"),
+    ];
+
+    let mut css = String::from(":root {\n");
+    for (name, custom, light, dark) in colors {
+        let default = match theme {
+            ExportTheme::Light => light,
+            ExportTheme::Dark => dark,
+        };
+        // Custom values end up inside the style element, so anything that
+        // could close the declaration is rejected
+        let value = custom
+            .filter(|c| !c.trim().is_empty() && !c.contains([';', '{', '}', '<', '>']))
+            .unwrap_or_else(|| default.to_string());
+        css.push_str(&format!("  --color-{}: {};\n", name, value.trim()));
+    }
+    css.push('}');
+    css.push_str(STYLE);
+    css
+}
+
+/// Escape text for HTML content and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A complete HTML document. `nav` is raw HTML shown above the content.
+pub fn page(title: &str, style: &str, nav: Option<&str>, body: &str) -> String {
+    let nav = nav.map(|nav| format!("<nav>{}</nav>\n", nav)).unwrap_or_default();
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}<main>\n{}</main>\n</body>\n</html>\n",
+        escape_html(title),
+        style,
+        nav,
+        body
+    )
+}
+
+/// Heading slugs in document order, matching the outline's anchors.
+fn heading_slugs(content: &str) -> Vec<String> {
+    fn walk(headings: Vec<outline::OutlineHeading>, slugs: &mut Vec<String>) {
+        for heading in headings {
+            slugs.push(heading.slug);
+            walk(heading.children, slugs);
+        }
+    }
+    let mut slugs = Vec::new();
+    walk(outline::parse_outline(content), &mut slugs);
+    slugs
+}
+
+/// How `render_note` writes an image or wiki embed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EmbedOutput {
+    Image,
+    /// A link to an embedded note
+    Link,
+    /// Just the embed's text, for an unresolved wiki embed
+    Text,
+}
+
+/// Render a note's markdown (GFM tables, task lists, strikethrough and
+/// footnotes) to HTML. Frontmatter is dropped and headings get the outline's
+/// anchors as IDs.
+///
+/// `rewrite` maps each link and image URL to the one to use in the output.
+/// None keeps the URL as written, except for wiki links, which become plain
+/// text because their targets are note names rather than URLs. A wiki embed
+/// that isn't rewritten is retried as a wiki link, since `![[note]]` embeds a
+/// note: it becomes a link if that gives a URL and plain text otherwise.
+pub fn render_note(content: &str, mut rewrite: impl FnMut(&str, RefKind) -> Option<String>) -> String {
+    let mut slugs = heading_slugs(content).into_iter();
+    // Whether each open link is kept (unrewritten wiki links are dropped)
+    let mut links: Vec<bool> = Vec::new();
+    // What each open image is written as
+    let mut images: Vec<EmbedOutput> = Vec::new();
+
+    let events = Parser::new_ext(content, markdown::parser_options()).filter_map(|event| match event {
+        Event::Start(Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        }) => {
+            let slug = slugs.next().map(CowStr::from);
+            Some(Event::Start(Tag::Heading {
+                level,
+                id: id.or(slug),
+                classes,
+                attrs,
+            }))
+        }
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let kind = if matches!(link_type, LinkType::WikiLink { .. }) {
+                RefKind::WikiLink
+            } else {
+                RefKind::Link
+            };
+            let dest_url = match rewrite(&dest_url, kind) {
+                Some(url) => CowStr::from(url),
+                None if kind == RefKind::WikiLink => {
+                    links.push(false);
+                    return None;
+                }
+                None => dest_url,
+            };
+            links.push(true);
+            Some(Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }))
+        }
+        Event::End(TagEnd::Link) => links.pop().unwrap_or(true).then_some(event),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let kind = if matches!(link_type, LinkType::WikiLink { .. }) {
+                RefKind::WikiEmbed
+            } else {
+                RefKind::Image
+            };
+            let (output, dest_url) = match rewrite(&dest_url, kind) {
+                Some(url) => (EmbedOutput::Image, CowStr::from(url)),
+                None if kind != RefKind::WikiEmbed => (EmbedOutput::Image, dest_url),
+                None => match rewrite(&dest_url, RefKind::WikiLink) {
+                    Some(url) => (EmbedOutput::Link, CowStr::from(url)),
+                    None => (EmbedOutput::Text, dest_url),
+                },
+            };
+            images.push(output);
+            let tag = match output {
+                EmbedOutput::Image => Tag::Image {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                },
+                EmbedOutput::Link => Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                },
+                EmbedOutput::Text => return None,
+            };
+            Some(Event::Start(tag))
+        }
+        Event::End(TagEnd::Image) => match images.pop().unwrap_or(EmbedOutput::Image) {
+            EmbedOutput::Image => Some(event),
+            EmbedOutput::Link => Some(Event::End(TagEnd::Link)),
+            EmbedOutput::Text => None,
+        },
+        event => Some(event),
+    });
+
+    let mut html = String::with_capacity(content.len() * 3 / 2);
+    pulldown_cmark::html::push_html(&mut html, events);
+    html
+}
+
+/// Percent-encode each segment of a POSIX path for use in a URL.
+pub fn encode_url_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Resolves the files notes reference and inlines or copies them for export.
+pub struct AssetExport<'a> {
+    notes_root: &'a Path,
+    lookup: AssetLookup<'a>,
+    mode: AssetMode,
+    /// Referenced files to copy, relative to the notes root
+    used: BTreeSet<String>,
+}
+
+impl<'a> AssetExport<'a> {
+    pub fn new(notes_root: &'a Path, assets: &'a [AssetFile], mode: AssetMode) -> Self {
+        Self {
+            notes_root,
+            lookup: AssetLookup::new(assets),
+            mode,
+            used: BTreeSet::new(),
+        }
+    }
+
+    /// URL for a reference to a file, or None if it isn't one of the folder's
+    /// assets. Inline mode embeds images and leaves other links alone; copy
+    /// mode links to the copy under the output folder, where `page_dir` is the
+    /// page's directory relative to that folder.
+    pub fn url(&mut self, note_id: &str, target: &str, kind: RefKind, page_dir: &str) -> Option<String> {
+        let decoded = urlencoding::decode(target)
+            .map(|t| t.into_owned())
+            .unwrap_or_else(|_| target.to_string());
+        let file_ref = FileRef {
+            target: decoded,
+            wiki_syntax: kind.is_wiki(),
+            span: (0, 0),
+        };
+        let path = *self.lookup.matches(self.notes_root, note_id, &file_ref).first()?;
+
+        match self.mode {
+            AssetMode::Inline => {
+                if !kind.is_image() {
+                    return None;
+                }
+                let extension = path.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+                let mime_type = attachments::mime_type_for_extension(extension);
+                let data = std::fs::read(self.notes_root.join(path)).ok()?;
+                Some(format!(
+                    "data:{};base64,{}",
+                    mime_type,
+                    base64::engine::general_purpose::STANDARD.encode(data)
+                ))
+            }
+            AssetMode::Copy => {
+                self.used.insert(path.to_string());
+                Some(encode_url_path(&crate::links::relative_path(page_dir, path)))
+            }
+        }
+    }
+
+    /// Copy the files `url` linked to into `out_dir`, keeping their paths
+    /// relative to the notes root. Returns the copied paths.
+    /// Fails rather than copying a file onto itself, which would empty it.
+    pub fn copy_used(&self, out_dir: &Path) -> std::io::Result<Vec<String>> {
+        for path in &self.used {
+            let source = self.notes_root.join(path);
+            let dest = out_dir.join(path);
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if let (Ok(source), Ok(dest)) = (source.canonicalize(), dest.canonicalize()) {
+                if source == dest {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::AlreadyExists,
+                        format!("{} would be copied onto itself", path),
+                    ));
+                }
+            }
+            std::fs::copy(&source, &dest)?;
+        }
+        Ok(self.used.iter().cloned().collect())
+    }
+}
+
+/// Whether two existing folders are the same on disk.
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+    matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Render one note to a standalone HTML file.
+pub fn export_note(
+    notes_root: &Path,
+    layout: &NoteLayout,
+    id: &str,
+    title: &str,
+    content: &str,
+    style: &str,
+    options: &HtmlExportOptions,
+) -> Result<(), String> {
+    let output = Path::new(&options.output_path);
+    if !output.is_absolute() {
+        return Err("The output path must be absolute".to_string());
+    }
+    let out_dir = output.parent().ok_or("The output path has no parent folder")?;
+    std::fs::create_dir_all(out_dir).map_err(|e| format!("Failed to create {}: {}", out_dir.display(), e))?;
+    if options.assets == AssetMode::Copy && is_same_dir(out_dir, notes_root) {
+        return Err("Copied assets can't be written into the notes folder itself".to_string());
+    }
+    let files = assets::list_assets(notes_root, layout);
+    let mut asset_export = AssetExport::new(notes_root, &files, options.assets);
+
+    let body = render_note(content, |url, kind| asset_export.url(id, url, kind, ""));
+    asset_export
+        .copy_used(out_dir)
+        .map_err(|e| format!("Failed to copy assets: {}", e))?;
+    std::fs::write(output, page(title, style, None, &body))
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+}
+
+/// A note to publish.
+pub struct SiteNote {
+    pub id: String,
+    pub content: String,
+}
+
+/// Entry of the client-side search index.
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    /// Page URL relative to the site root
+    url: String,
+    title: &'a str,
+    tags: &'a [String],
+    text: String,
+}
+
+/// Directory part of a POSIX path.
+fn dir_of(path: &str) -> &str {
+    path.rfind('/').map(|pos| &path[..pos]).unwrap_or("")
+}
+
+/// Site path of a note's page.
+fn note_page(id: &str) -> String {
+    format!("{}/{}.html", NOTES_DIR, id)
+}
+
+/// Site path of a tag's page. Nested tags (`a/b`) get nested folders.
+fn tag_page(tag: &str) -> String {
+    format!("{}/{}.html", TAGS_DIR, tag)
+}
+
+/// URL of site path `to` from a page in directory `from_dir`.
+fn site_url(from_dir: &str, to: &str) -> String {
+    encode_url_path(&links::relative_path(from_dir, to))
+}
+
+/// Site URL for a link in note `source_id` that points at another note (or a
+/// heading in one), or None to leave the link as written. Wiki link anchors
+/// are heading text, so they're turned into the heading's slug.
+fn note_link_url(resolver: &Resolver, notes_root: &Path, source_id: &str, url: &str, kind: RefKind) -> Option<String> {
+    let (target, anchor) = match url.split_once('#') {
+        Some((target, anchor)) => (target, Some(anchor).filter(|a| !a.is_empty())),
+        None => (url, None),
+    };
+    let anchor = anchor.map(|anchor| match kind {
+        RefKind::WikiLink => markdown::heading_slug(anchor),
+        _ => anchor.to_string(),
+    });
+
+    let page = if target.is_empty() {
+        // Same-page anchor; markdown ones already work as written
+        if kind != RefKind::WikiLink {
+            return None;
+        }
+        String::new()
+    } else {
+        let target = match kind {
+            RefKind::WikiLink => target.to_string(),
+            _ => urlencoding::decode(target).map(|t| t.into_owned()).unwrap_or_else(|_| target.to_string()),
+        };
+        match resolver.resolve_target(notes_root, source_id, &target, kind == RefKind::WikiLink) {
+            Resolution::Note(id) => site_url(dir_of(&note_page(source_id)), &note_page(&id)),
+            _ => return None,
+        }
+    };
+
+    match anchor {
+        Some(anchor) => Some(format!("{}#{}", page, anchor)),
+        None => Some(page),
+    }
+}
+
+/// A `<ul>` of links to notes as (ID, title, preview), from a page in `from_dir`.
+fn note_list(notes: &[(&str, &str, String)], from_dir: &str) -> String {
+    let mut html = String::from("<ul class=\"note-list\">\n");
+    for (id, title, preview) in notes {
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            site_url(from_dir, &note_page(id)),
+            escape_html(title)
+        ));
+        if !preview.is_empty() {
+            html.push_str(&format!("<br><span class=\"muted\">{}</span>", escape_html(preview)));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+/// Navigation bar: a link back to the index and the page's tags.
+fn site_nav(site_title: &str, from_dir: &str, tags: &[String]) -> String {
+    let mut nav = format!(
+        "<a href=\"{}\">{}</a>",
+        site_url(from_dir, "index.html"),
+        escape_html(site_title)
+    );
+    for tag in tags {
+        nav.push_str(&format!(
+            " · <a href=\"{}\">#{}</a>",
+            site_url(from_dir, &tag_page(tag)),
+            escape_html(tag)
+        ));
+    }
+    nav
+}
+
+/// Search box for the index page: loads the search index and lists notes
+/// whose title, tags or text contain every word typed.
+const SEARCH_SCRIPT: &str = r#"<input type="search" id="search" placeholder="Search" autocomplete="off">
+<ul class="note-list" id="search-results"></ul>
+<script src="search-index.js"></script>
+<script>
+(function () {
+  var input = document.getElementById("search");
+  var results = document.getElementById("search-results");
+  var index = window.searchIndex || null;
+  function escape(text) {
+    var div = document.createElement("div");
+    div.textContent = text;
+    return div.innerHTML;
+  }
+  function render() {
+    var words = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    if (!index || words.length === 0) { results.innerHTML = ""; return; }
+    var matches = index.filter(function (entry) {
+      var haystack = (entry.title + " " + entry.tags.join(" ") + " " + entry.text).toLowerCase();
+      return words.every(function (word) { return haystack.indexOf(word) !== -1; });
+    });
+    results.innerHTML = matches.slice(0, 50).map(function (entry) {
+      return '<li><a href="' + encodeURI(entry.url) + '">' + escape(entry.title) + "</a></li>";
+    }).join("") || '<li class="muted">No results</li>';
+  }
+  input.addEventListener("input", render);
+})();
+</script>
+"#;
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Render `notes` to a static site in `out_dir`: a page per note under
+/// `notes/` with note and wiki links pointing at the other pages, the assets
+/// they use, an index page with a search box, a page per tag and the search
+/// index script.
+pub fn export_site(
+    notes_root: &Path,
+    layout: &NoteLayout,
+    out_dir: &Path,
+    notes: &[SiteNote],
+    site_title: &str,
+    style: &str,
+) -> Result<SiteExportReport, String> {
+    let ids: BTreeSet<String> = notes.iter().map(|note| note.id.clone()).collect();
+    let resolver = Resolver::new(&ids, layout);
+    let files = assets::list_assets(notes_root, layout);
+    let mut asset_export = AssetExport::new(notes_root, &files, AssetMode::Copy);
+
+    // (note, title, tags, preview) of every page written
+    let mut listed: Vec<(&SiteNote, String, Vec<String>, String)> = Vec::with_capacity(notes.len());
+
+    for note in notes {
+        let title = preview::extract_title(&note.content);
+        let note_tags = tags::extract_tags(&note.content);
+        let page_path = note_page(&note.id);
+        let page_dir = dir_of(&page_path);
+
+        let body = render_note(&note.content, |url, kind| {
+            asset_export
+                .url(&note.id, url, kind, page_dir)
+                .or_else(|| match kind {
+                    RefKind::Link | RefKind::WikiLink => note_link_url(&resolver, notes_root, &note.id, url, kind),
+                    RefKind::Image | RefKind::WikiEmbed => None,
+                })
+        });
+        let nav = site_nav(site_title, page_dir, &note_tags);
+        write_file(&out_dir.join(&page_path), &page(&title, style, Some(&nav), &body))?;
+
+        let preview = preview::generate_preview(&note.content);
+        listed.push((note, title, note_tags, preview));
+    }
+    listed.sort_by(|a, b| a.1.to_lowercase().cmp(&b.1.to_lowercase()).then(a.0.id.cmp(&b.0.id)));
+
+    let mut by_tag: BTreeMap<&str, Vec<(&str, &str, String)>> = BTreeMap::new();
+    for (note, title, note_tags, _) in &listed {
+        for tag in note_tags {
+            by_tag.entry(tag).or_default().push((&note.id, title, String::new()));
+        }
+    }
+    let search: Vec<SearchEntry> = listed
+        .iter()
+        .map(|(note, title, note_tags, _)| SearchEntry {
+            url: note_page(&note.id),
+            title,
+            tags: note_tags,
+            text: preview::plain_text(&note.content),
+        })
+        .collect();
+
+    for (tag, tagged) in &by_tag {
+        let page_path = tag_page(tag);
+        let page_dir = dir_of(&page_path);
+        let body = format!("<h1>#{}</h1>\n{}", escape_html(tag), note_list(tagged, page_dir));
+        let nav = site_nav(site_title, page_dir, &[]);
+        write_file(&out_dir.join(&page_path), &page(&format!("#{}", tag), style, Some(&nav), &body))?;
+    }
+
+    let mut index = format!("<h1>{}</h1>\n{}", escape_html(site_title), SEARCH_SCRIPT);
+    let all: Vec<(&str, &str, String)> = listed
+        .iter()
+        .map(|(note, title, _, preview)| (note.id.as_str(), title.as_str(), preview.clone()))
+        .collect();
+    index.push_str(&note_list(&all, ""));
+    if !by_tag.is_empty() {
+        index.push_str("<h2>Tags</h2>\n<p>");
+        let tag_links: Vec<String> = by_tag
+            .iter()
+            .map(|(tag, tagged)| {
+                format!(
+                    "<a href=\"{}\">#{}</a> <span class=\"muted\">{}</span>",
+                    site_url("", &tag_page(tag)),
+                    escape_html(tag),
+                    tagged.len()
+                )
+            })
+            .collect();
+        index.push_str(&tag_links.join(" · "));
+        index.push_str("</p>\n");
+    }
+    write_file(&out_dir.join("index.html"), &page(site_title, style, None, &index))?;
+
+    let search_json = serde_json::to_string(&search).map_err(|e| e.to_string())?;
+    write_file(&out_dir.join(SEARCH_INDEX), &format!("window.searchIndex = {};\n", search_json))?;
+
+    let copied = asset_export
+        .copy_used(out_dir)
+        .map_err(|e| format!("Failed to copy assets: {}", e))?;
+
+    Ok(SiteExportReport {
+        notes: notes.len(),
+        tags: by_tag.len(),
+        assets: copied,
+        skipped: Vec::new(),
+    })
+}
@@ -0,0 +1,720 @@
+//! Markdown formatter: list markers, emphasis, heading style, table alignment,
+//! line wrapping and blank lines.
+//!
+//! Formatting is a series of passes that each edit the source in place. A pass
+//! is only kept if the document parses to the same events afterwards (text
+//! whitespace aside), so formatting never changes what a note says.
+
+use crate::line_endings::{self, LineEnding};
+use crate::{cjk, markdown};
+use pulldown_cmark::{Alignment, CowStr, Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+const BULLETS: [char; 3] = ['-', '*', '+'];
+
+/// Narrowest wrapping width accepted.
+const MIN_LINE_WIDTH: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FormatOptions {
+    /// Unordered list marker: `-`, `*` or `+`
+    pub bullet: char,
+    /// Emphasis delimiter: `*` or `_`
+    pub emphasis: char,
+    /// Strong emphasis delimiter, written twice: `*` or `_`
+    pub strong: char,
+    /// Rewrite Setext (underlined) headings as ATX (`#`) headings
+    pub atx_headings: bool,
+    /// Pad table cells so the columns line up
+    pub align_tables: bool,
+    /// Reflow paragraphs to this many columns; None keeps the existing line breaks
+    pub line_width: Option<usize>,
+    /// Strip trailing whitespace, collapse runs of blank lines and put blank
+    /// lines around top-level headings
+    pub normalize_blank_lines: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            bullet: '-',
+            emphasis: '*',
+            strong: '*',
+            atx_headings: true,
+            align_tables: true,
+            line_width: None,
+            normalize_blank_lines: true,
+        }
+    }
+}
+
+impl FormatOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !BULLETS.contains(&self.bullet) {
+            return Err(format!("Invalid list marker: {}", self.bullet));
+        }
+        if !matches!(self.emphasis, '*' | '_') || !matches!(self.strong, '*' | '_') {
+            return Err("Emphasis markers must be * or _".to_string());
+        }
+        if self.line_width.is_some_and(|w| w < MIN_LINE_WIDTH) {
+            return Err(format!("Line width must be at least {}", MIN_LINE_WIDTH));
+        }
+        Ok(())
+    }
+}
+
+/// Format a markdown document. Line endings are kept.
+pub fn format_markdown(content: &str, options: &FormatOptions) -> Result<String, String> {
+    options.validate()?;
+    let ending = line_endings::detect(content).unwrap_or_default();
+    let mut text = line_endings::apply(content, LineEnding::Lf);
+    let reference = semantic_events(&text);
+
+    let passes: [fn(&str, &FormatOptions) -> Option<String>; 5] =
+        [normalize_markers, atx_headings, align_tables, wrap_paragraphs, normalize_blank_lines];
+    for pass in passes {
+        if let Some(candidate) = pass(&text, options) {
+            if candidate != text && semantic_events(&candidate) == reference {
+                text = candidate;
+            }
+        }
+    }
+
+    Ok(line_endings::apply(&text, ending))
+}
+
+fn parse(content: &str) -> impl Iterator<Item = (Event<'_>, Range<usize>)> {
+    Parser::new_ext(content, markdown::parser_options()).into_offset_iter()
+}
+
+/// The parsed document, for checking that formatting kept its meaning.
+/// Adjacent text is merged, and in text that renders as flowing prose soft
+/// breaks count as spaces and runs of spaces, tabs and newlines as one space.
+/// Code, HTML and frontmatter blocks, and the rest of a paragraph after inline
+/// HTML (which may open a `<pre>`), are compared exactly.
+fn semantic_events(content: &str) -> Vec<Event<'static>> {
+    let mut events: Vec<Event<'static>> = Vec::new();
+    let mut verbatim = 0usize;
+    // Inline HTML seen in the current block
+    let mut raw_inline = false;
+    for (event, _) in parse(content) {
+        match &event {
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)) => verbatim += 1,
+            Event::End(TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_)) => {
+                verbatim = verbatim.saturating_sub(1)
+            }
+            _ => {}
+        }
+        match &event {
+            Event::InlineHtml(_) => raw_inline = true,
+            Event::Start(tag) if !markdown::is_inline_tag(TagEnd::from(tag.clone())) => raw_inline = false,
+            Event::End(tag) if !markdown::is_inline_tag(*tag) => raw_inline = false,
+            _ => {}
+        }
+        let flowing = verbatim == 0 && !raw_inline;
+        let event = match event {
+            Event::SoftBreak if flowing => Event::Text(" ".into()),
+            event => event,
+        };
+        if let (Some(Event::Text(prev)), Event::Text(next)) = (events.last_mut(), &event) {
+            let mut merged = format!("{}{}", prev, next);
+            if flowing {
+                merged = collapse_whitespace(&merged);
+            }
+            *prev = CowStr::from(merged);
+            continue;
+        }
+        let event = match event {
+            Event::Text(text) if flowing => Event::Text(collapse_whitespace(&text).into()),
+            event => event,
+        };
+        events.push(event.into_static());
+    }
+    events
+}
+
+/// Runs of HTML whitespace as one space. Other spaces (no-break, ideographic)
+/// are kept, since they render as they are.
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C') {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+/// Unordered list markers and emphasis delimiters.
+fn normalize_markers(content: &str, options: &FormatOptions) -> Option<String> {
+    let bytes = content.as_bytes();
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    // Marker chosen for each open list (None for ordered lists)
+    let mut lists: Vec<Option<char>> = Vec::new();
+    // Marker of an unordered list that just ended, so a list right after it
+    // keeps a different marker instead of merging into it
+    let mut previous_list: Option<(usize, char)> = None;
+
+    for (event, range) in parse(content) {
+        let follows_list = previous_list.take();
+        match event {
+            Event::Start(Tag::List(None)) => {
+                let original = bytes.get(range.start).map(|b| *b as char).unwrap_or(options.bullet);
+                let marker = match follows_list {
+                    Some((depth, used)) if depth == lists.len() && used == options.bullet => {
+                        if original != used {
+                            original
+                        } else {
+                            BULLETS.into_iter().find(|b| *b != used).unwrap_or(original)
+                        }
+                    }
+                    _ => options.bullet,
+                };
+                lists.push(Some(marker));
+            }
+            Event::Start(Tag::List(Some(_))) => lists.push(None),
+            Event::End(TagEnd::List(ordered)) => {
+                let marker = lists.pop().flatten();
+                if let (false, Some(marker)) = (ordered, marker) {
+                    previous_list = Some((lists.len(), marker));
+                }
+            }
+            Event::Start(Tag::Item) => {
+                if let Some(Some(marker)) = lists.last() {
+                    let current = bytes[range.start] as char;
+                    if BULLETS.contains(&current) && current != *marker {
+                        edits.push((range.start, range.start + 1, marker.to_string()));
+                    }
+                }
+            }
+            Event::Start(Tag::Emphasis) => {
+                push_delimiter_edits(content, &range, 1, options.emphasis, &mut edits);
+            }
+            Event::Start(Tag::Strong) => {
+                push_delimiter_edits(content, &range, 2, options.strong, &mut edits);
+            }
+            _ => {}
+        }
+    }
+
+    Some(markdown::apply_edits(content, edits))
+}
+
+/// Swap the `len`-character delimiters at both ends of an emphasis span.
+/// Underscores don't work inside words, so those spans are left alone.
+fn push_delimiter_edits(
+    content: &str,
+    range: &Range<usize>,
+    len: usize,
+    target: char,
+    edits: &mut Vec<(usize, usize, String)>,
+) {
+    if range.len() < len * 2 + 1 {
+        return;
+    }
+    let open = &content[range.start..range.start + len];
+    let close = &content[range.end - len..range.end];
+    let wanted = target.to_string().repeat(len);
+    if open == wanted || open != close || !open.chars().all(|c| c == '*' || c == '_') {
+        return;
+    }
+    let before = content[..range.start].chars().next_back();
+    let after = content[range.end..].chars().next();
+    if target == '_' && (before.is_some_and(char::is_alphanumeric) || after.is_some_and(char::is_alphanumeric)) {
+        return;
+    }
+    edits.push((range.start, range.start + len, wanted.clone()));
+    edits.push((range.end - len, range.end, wanted));
+}
+
+/// Join the source of an inline run onto one line, dropping line breaks and
+/// the container prefixes (`>`, indentation) after them.
+fn single_line(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut lines = source.split('\n');
+    if let Some(first) = lines.next() {
+        out.push_str(first.trim_end());
+    }
+    for line in lines {
+        out.push(' ');
+        out.push_str(line.trim_start_matches([' ', '\t', '>']).trim_end());
+    }
+    out
+}
+
+/// Setext headings rewritten as ATX headings.
+fn atx_headings(content: &str, options: &FormatOptions) -> Option<String> {
+    if !options.atx_headings {
+        return None;
+    }
+    let mut edits = Vec::new();
+    // (level, heading range, inline content range)
+    let mut heading: Option<(usize, Range<usize>, Option<Range<usize>>)> = None;
+
+    for (event, range) in parse(content) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                let is_setext = !content[range.clone()].trim_start().starts_with('#');
+                heading = is_setext.then_some((level as usize, range, None));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, range, Some(inline))) = heading.take() {
+                    let newline = if content[range.clone()].ends_with('\n') { "\n" } else { "" };
+                    let text = single_line(&content[inline]);
+                    edits.push((range.start, range.end, format!("{} {}{}", "#".repeat(level), text, newline)));
+                }
+            }
+            _ => {
+                if let Some((_, _, ref mut inline)) = heading {
+                    *inline = Some(match inline.take() {
+                        Some(open) => open.start..open.end.max(range.end),
+                        None => range,
+                    });
+                }
+            }
+        }
+    }
+
+    Some(markdown::apply_edits(content, edits))
+}
+
+/// Start of the line containing byte `pos`.
+fn line_start(content: &str, pos: usize) -> usize {
+    content[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Tables with padded cells and matching delimiter rows. Tables inside block
+/// quotes are left alone.
+fn align_tables(content: &str, options: &FormatOptions) -> Option<String> {
+    if !options.align_tables {
+        return None;
+    }
+    let mut edits = Vec::new();
+    let mut table: Option<(Range<usize>, Vec<Alignment>)> = None;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+
+    for (event, range) in parse(content) {
+        match event {
+            Event::Start(Tag::Table(alignments)) => {
+                table = Some((range, alignments));
+                rows.clear();
+            }
+            Event::Start(Tag::TableHead | Tag::TableRow) => rows.push(Vec::new()),
+            Event::Start(Tag::TableCell) => {
+                if let Some(row) = rows.last_mut() {
+                    row.push(content[range].trim().to_string());
+                }
+            }
+            Event::End(TagEnd::Table) => {
+                let Some((range, alignments)) = table.take() else {
+                    continue;
+                };
+                let indent = &content[line_start(content, range.start)..range.start];
+                if !indent.chars().all(|c| c == ' ') {
+                    continue;
+                }
+                let mut rendered = render_table(&rows, &alignments).join(&format!("\n{}", indent));
+                if content[range.clone()].ends_with('\n') {
+                    rendered.push('\n');
+                }
+                edits.push((range.start, range.end, rendered));
+            }
+            _ => {}
+        }
+    }
+
+    Some(markdown::apply_edits(content, edits))
+}
+
+fn render_table(rows: &[Vec<String>], alignments: &[Alignment]) -> Vec<String> {
+    let columns = alignments.len();
+    let mut widths = vec![3; columns];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cjk::display_width(cell));
+        }
+    }
+
+    let render_row = |row: &[String]| {
+        let cells: Vec<String> = (0..columns)
+            .map(|i| {
+                let cell = row.get(i).map(String::as_str).unwrap_or("");
+                let pad = widths[i] - cjk::display_width(cell);
+                match alignments[i] {
+                    Alignment::Right => format!("{}{}", " ".repeat(pad), cell),
+                    Alignment::Center => format!("{}{}{}", " ".repeat(pad / 2), cell, " ".repeat(pad - pad / 2)),
+                    Alignment::Left | Alignment::None => format!("{}{}", cell, " ".repeat(pad)),
+                }
+            })
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let delimiters: Vec<String> = alignments
+        .iter()
+        .zip(&widths)
+        .map(|(alignment, &width)| match alignment {
+            Alignment::None => "-".repeat(width),
+            Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+            Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+            Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+        })
+        .collect();
+
+    let mut lines = Vec::with_capacity(rows.len() + 1);
+    if let Some((header, body)) = rows.split_first() {
+        lines.push(render_row(header));
+        lines.push(format!("| {} |", delimiters.join(" | ")));
+        lines.extend(body.iter().map(|row| render_row(row)));
+    }
+    lines
+}
+
+/// A run of inline content in a paragraph or tight list item.
+#[derive(Default)]
+struct InlineRun {
+    range: Option<Range<usize>>,
+    /// Spans that must stay on one line (code, links, inline HTML)
+    atoms: Vec<Range<usize>>,
+    hard_breaks: Vec<Range<usize>>,
+    /// Whether the run holds inline HTML, whose whitespace may be significant
+    has_html: bool,
+}
+
+impl InlineRun {
+    fn extend(&mut self, range: &Range<usize>) {
+        self.range = Some(match self.range.take() {
+            Some(run) => run.start..run.end.max(range.end),
+            None => range.clone(),
+        });
+    }
+}
+
+/// Paragraphs reflowed to the configured width.
+fn wrap_paragraphs(content: &str, options: &FormatOptions) -> Option<String> {
+    let width = options.line_width?;
+    let mut edits = Vec::new();
+    // Whether each open block holds wrappable inline content
+    let mut blocks: Vec<bool> = Vec::new();
+    let mut run = InlineRun::default();
+    // Depth inside links and images, whose contents belong to the atom
+    let mut atom_depth = 0usize;
+
+    let finish = |run: &mut InlineRun, edits: &mut Vec<(usize, usize, String)>| {
+        let run = std::mem::take(run);
+        if let Some(range) = run.range.clone() {
+            if let Some(wrapped) = reflow(content, &run, width) {
+                edits.push((range.start, range.end, wrapped));
+            }
+        }
+    };
+
+    for (event, range) in parse(content) {
+        let wrappable = blocks.last().copied().unwrap_or(false);
+        match event {
+            Event::Start(Tag::Link { .. } | Tag::Image { .. }) => {
+                if atom_depth == 0 && wrappable {
+                    run.extend(&range);
+                    run.atoms.push(range);
+                }
+                atom_depth += 1;
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => atom_depth = atom_depth.saturating_sub(1),
+            _ if atom_depth > 0 => {}
+            Event::Start(tag) if !markdown::is_inline_tag(TagEnd::from(tag.clone())) => {
+                finish(&mut run, &mut edits);
+                blocks.push(matches!(tag, Tag::Paragraph | Tag::Item));
+            }
+            Event::End(tag) if !markdown::is_inline_tag(tag) => {
+                finish(&mut run, &mut edits);
+                blocks.pop();
+            }
+            Event::Code(_) | Event::InlineHtml(_) | Event::FootnoteReference(_) | Event::InlineMath(_) if wrappable => {
+                run.has_html |= matches!(event, Event::InlineHtml(_));
+                run.extend(&range);
+                run.atoms.push(range);
+            }
+            Event::HardBreak if wrappable => {
+                run.extend(&range);
+                run.hard_breaks.push(range);
+            }
+            Event::Text(_) | Event::SoftBreak | Event::Start(_) | Event::End(_) if wrappable => run.extend(&range),
+            _ => finish(&mut run, &mut edits),
+        }
+    }
+    finish(&mut run, &mut edits);
+
+    Some(markdown::apply_edits(content, edits))
+}
+
+/// Whether a line may start with `word` without turning into a different block.
+fn can_start_line(word: &str) -> bool {
+    let is_ordered_marker = word.len() >= 2
+        && word.len() <= 10
+        && word[..word.len() - 1].chars().all(|c| c.is_ascii_digit())
+        && word.ends_with(['.', ')']);
+    !(is_ordered_marker
+        || word.chars().all(|c| matches!(c, '#' | '-' | '=' | '_' | '*' | '+'))
+        || word.starts_with(['>', '<', '|'])
+        || word.starts_with("```")
+        || word.starts_with("~~~"))
+}
+
+/// Greedy reflow of one inline run. None when it can't be wrapped safely.
+fn reflow(content: &str, run: &InlineRun, width: usize) -> Option<String> {
+    let range = run.range.clone()?;
+    if run.has_html || run.atoms.iter().any(|atom| content[atom.clone()].contains('\n')) {
+        return None;
+    }
+
+    let first_prefix = &content[line_start(content, range.start)..range.start];
+    let continuation: String = first_prefix
+        .chars()
+        .map(|c| if matches!(c, '>' | ' ' | '\t') { c } else { ' ' })
+        .collect();
+    let first_width = width.saturating_sub(cjk::display_width(first_prefix));
+    let rest_width = width.saturating_sub(cjk::display_width(&continuation));
+
+    // Lines separated by hard breaks, each a list of words
+    let mut segments: Vec<(Vec<&str>, Option<&str>)> = vec![(Vec::new(), None)];
+    let mut pos = range.start;
+    let mut word_start: Option<usize> = None;
+    while pos < range.end {
+        if let Some(atom) = run.atoms.iter().find(|a| a.start == pos) {
+            word_start.get_or_insert(pos);
+            pos = atom.end;
+            continue;
+        }
+        if let Some(hard_break) = run.hard_breaks.iter().find(|b| b.start == pos) {
+            let segment = segments.last_mut().expect("segments is non-empty");
+            if let Some(start) = word_start.take() {
+                segment.0.push(&content[start..pos]);
+            }
+            segment.1 = Some(content[hard_break.clone()].trim_end_matches('\n'));
+            segments.push((Vec::new(), None));
+            pos = hard_break.end;
+            // Skip the container prefix on the next line
+            pos += content[pos..range.end].len() - content[pos..range.end].trim_start_matches([' ', '\t', '>']).len();
+            continue;
+        }
+        let c = content[pos..].chars().next()?;
+        if c.is_whitespace() {
+            if let Some(start) = word_start.take() {
+                segments.last_mut().expect("segments is non-empty").0.push(&content[start..pos]);
+            }
+            pos += c.len_utf8();
+            if c == '\n' {
+                pos += content[pos..range.end].len() - content[pos..range.end].trim_start_matches([' ', '\t', '>']).len();
+            }
+        } else {
+            word_start.get_or_insert(pos);
+            pos += c.len_utf8();
+        }
+    }
+    if let Some(start) = word_start {
+        segments.last_mut().expect("segments is non-empty").0.push(&content[start..range.end]);
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+    for (words, hard_break) in &segments {
+        for word in words {
+            let word_width = cjk::display_width(word);
+            let limit = if lines.is_empty() { first_width } else { rest_width };
+            if !line.is_empty() && line_width + 1 + word_width > limit && can_start_line(word) {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+                line_width += 1;
+            }
+            line.push_str(word);
+            line_width += word_width;
+        }
+        if let Some(hard_break) = hard_break {
+            line.push_str(hard_break);
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    Some(lines.join(&format!("\n{}", continuation)))
+}
+
+/// Trailing whitespace stripped, blank line runs collapsed and top-level
+/// headings surrounded by blank lines. Code, HTML and frontmatter are untouched.
+fn normalize_blank_lines(content: &str, options: &FormatOptions) -> Option<String> {
+    if !options.normalize_blank_lines {
+        return None;
+    }
+    let mut verbatim: Vec<Range<usize>> = Vec::new();
+    // Lines ending in a trailing-space hard break, by start offset
+    let mut hard_break_lines: Vec<usize> = Vec::new();
+    let mut headings: Vec<Range<usize>> = Vec::new();
+    let mut depth = 0usize;
+
+    for (event, range) in parse(content) {
+        match event {
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)) => verbatim.push(range),
+            Event::Start(Tag::Heading { .. }) if depth == 0 => headings.push(range),
+            Event::HardBreak if content[range.clone()].starts_with([' ', '\t']) => {
+                hard_break_lines.push(line_start(content, range.start));
+            }
+            _ => {}
+        }
+        match event {
+            Event::Start(Tag::BlockQuote(_) | Tag::List(_) | Tag::FootnoteDefinition(_) | Tag::DefinitionList) => {
+                depth += 1
+            }
+            Event::End(TagEnd::BlockQuote(_) | TagEnd::List(_) | TagEnd::FootnoteDefinition | TagEnd::DefinitionList) => {
+                depth = depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = String::with_capacity(content.len());
+    let mut previous_blank = true;
+    let mut blank_after_heading = false;
+    let mut offset = 0;
+    for raw in content.split_inclusive('\n') {
+        let start = offset;
+        offset += raw.len();
+        let line = raw.strip_suffix('\n').unwrap_or(raw);
+        let end = start + line.len();
+
+        if verbatim.iter().any(|r| r.start < offset && start < r.end) {
+            out.push_str(line);
+            out.push('\n');
+            previous_blank = line.trim().is_empty();
+            blank_after_heading = false;
+            continue;
+        }
+
+        let line = if hard_break_lines.contains(&start) { line } else { line.trim_end() };
+        if line.is_empty() {
+            if !previous_blank {
+                out.push('\n');
+            }
+            previous_blank = true;
+            blank_after_heading = false;
+            continue;
+        }
+
+        let starts_heading = headings.iter().any(|h| h.start >= start && h.start <= end);
+        if (starts_heading || blank_after_heading) && !previous_blank {
+            out.push('\n');
+        }
+        out.push_str(line);
+        out.push('\n');
+        previous_blank = false;
+        blank_after_heading = headings.iter().any(|h| {
+            let last = h.end.saturating_sub(1).max(h.start);
+            last >= start && last <= end
+        });
+    }
+
+    while out.ends_with("\n\n") {
+        out.pop();
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Format with only the given pass enabled (plus marker normalisation,
+    /// which has no switch), and check the result parses like the input.
+    fn format(content: &str, options: FormatOptions) -> String {
+        let out = format_markdown(content, &options).expect("valid options");
+        assert_eq!(semantic_events(&out), semantic_events(content), "meaning changed:\n{}", out);
+        out
+    }
+
+    fn only(enable: impl FnOnce(&mut FormatOptions)) -> FormatOptions {
+        let mut options = FormatOptions {
+            atx_headings: false,
+            align_tables: false,
+            normalize_blank_lines: false,
+            ..FormatOptions::default()
+        };
+        enable(&mut options);
+        options
+    }
+
+    #[test]
+    fn tables_align_by_display_width() {
+        let input = "| 名前 | n |\n|:-|--:|\n| 東京都 | 12 |\n| ab | 3 |\n";
+        let out = format(input, only(|o| o.align_tables = true));
+        assert_eq!(
+            out,
+            "| 名前   |   n |\n| :----- | --: |\n| 東京都 |  12 |\n| ab     |   3 |\n"
+        );
+    }
+
+    #[test]
+    fn nested_list_markers_are_normalized() {
+        let input = "* one\n  + nested\n    * deeper\n* two\n\n1. first\n   + inside ordered\n";
+        let out = format(input, only(|_| {}));
+        assert_eq!(out, "- one\n  - nested\n    - deeper\n- two\n\n1. first\n   - inside ordered\n");
+    }
+
+    #[test]
+    fn wrapping_keeps_quote_and_list_prefixes() {
+        let input = "> alpha beta gamma delta epsilon zeta eta theta\n\n- iota kappa lambda mu nu xi omicron pi\n";
+        let out = format(input, only(|o| o.line_width = Some(20)));
+        assert_eq!(
+            out,
+            "> alpha beta gamma\n> delta epsilon zeta\n> eta theta\n\n\
+             - iota kappa lambda\n  mu nu xi omicron\n  pi\n"
+        );
+    }
+
+    #[test]
+    fn hard_breaks_survive_wrapping_and_blank_line_cleanup() {
+        let input = "one two three four five six  \nseven\\\neight nine\n";
+        let out = format(
+            input,
+            only(|o| {
+                o.line_width = Some(20);
+                o.normalize_blank_lines = true;
+            }),
+        );
+        assert_eq!(out, "one two three four\nfive six  \nseven\\\neight nine\n");
+    }
+
+    #[test]
+    fn code_html_and_frontmatter_are_untouched() {
+        let input = "---\ntags:   [a,   b]   \n---\n\n```\n*  raw   text    \n\n\n```\n\n\
+                     \x20   indented   code  \n\n<div>\n  *  kept   *\n</div>\n";
+        let options = FormatOptions {
+            line_width: Some(20),
+            ..FormatOptions::default()
+        };
+        let out = format(input, options);
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn whitespace_inside_inline_html_is_significant() {
+        let text = "before <pre>a   b</pre> after\n";
+        assert_ne!(semantic_events(text), semantic_events("before <pre>a b</pre> after\n"));
+        let wrapped = format(text, only(|o| o.line_width = Some(20)));
+        assert_eq!(wrapped, text);
+        assert_ne!(semantic_events("a\u{a0}\u{a0}b\n"), semantic_events("a\u{a0}b\n"));
+    }
+}
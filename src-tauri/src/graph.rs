@@ -0,0 +1,213 @@
+//! Whole-folder note graph for visualisation, with GraphML and DOT export.
+
+use crate::links::{LinkGraph, LinkKind};
+use crate::tags::TagIndex;
+use crate::NoteLayout;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+/// Number of best-connected notes reported as hubs.
+const HUB_COUNT: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub degree: usize,
+}
+
+// Links of one kind from one note to another, collapsed into a single edge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: LinkKind,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub average_degree: f32,
+    /// Notes without any links in or out
+    pub orphans: Vec<String>,
+    /// Most connected notes, highest degree first
+    pub hubs: Vec<GraphNode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    pub stats: GraphStats,
+}
+
+/// Build the note graph from the folder's link graph and tag index.
+/// Self-links are left out; degree counts the notes linked to and from a note,
+/// whatever the kind of link.
+pub fn build(link_graph: &LinkGraph, tag_index: &TagIndex, notes_root: &Path, layout: &NoteLayout) -> NoteGraph {
+    let mut collapsed: BTreeMap<(String, String, LinkKind), usize> = BTreeMap::new();
+    for (source, target, kind) in link_graph.resolved_edges(notes_root, layout) {
+        if source != target {
+            *collapsed.entry((source, target, kind)).or_insert(0) += 1;
+        }
+    }
+
+    // A wiki link and a markdown link to the same note are one connection
+    let linked_pairs: BTreeSet<(&str, &str)> = collapsed
+        .keys()
+        .map(|(source, target, _)| (source.as_str(), target.as_str()))
+        .collect();
+    let mut degrees: HashMap<&str, usize> = HashMap::new();
+    for (source, target) in linked_pairs {
+        *degrees.entry(source).or_insert(0) += 1;
+        *degrees.entry(target).or_insert(0) += 1;
+    }
+
+    let nodes: Vec<GraphNode> = link_graph
+        .notes()
+        .map(|id| GraphNode {
+            id: id.to_string(),
+            title: link_graph
+                .title_of(id)
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| crate::extract_title_from_id(id)),
+            tags: tag_index.tags_of(id),
+            degree: degrees.get(id).copied().unwrap_or(0),
+        })
+        .collect();
+
+    let edges: Vec<GraphEdge> = collapsed
+        .into_iter()
+        .map(|((source, target, kind), count)| GraphEdge {
+            source,
+            target,
+            kind,
+            count,
+        })
+        .collect();
+
+    let orphans = nodes
+        .iter()
+        .filter(|n| n.degree == 0)
+        .map(|n| n.id.clone())
+        .collect();
+
+    let mut hubs: Vec<GraphNode> = nodes.iter().filter(|n| n.degree > 0).cloned().collect();
+    hubs.sort_by(|a, b| b.degree.cmp(&a.degree).then_with(|| a.id.cmp(&b.id)));
+    hubs.truncate(HUB_COUNT);
+
+    let average_degree = if nodes.is_empty() {
+        0.0
+    } else {
+        nodes.iter().map(|n| n.degree).sum::<usize>() as f32 / nodes.len() as f32
+    };
+
+    NoteGraph {
+        stats: GraphStats {
+            node_count: nodes.len(),
+            edge_count: edges.len(),
+            average_degree,
+            orphans,
+            hubs,
+        },
+        nodes,
+        edges,
+    }
+}
+
+fn kind_name(kind: LinkKind) -> &'static str {
+    match kind {
+        LinkKind::Wiki => "wiki",
+        LinkKind::Markdown => "markdown",
+        LinkKind::Embed => "embed",
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Serialize the graph as GraphML (directed, with title/tags/degree and kind/count attributes).
+pub fn to_graphml(graph: &NoteGraph) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    out.push_str("  <key id=\"title\" for=\"node\" attr.name=\"title\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"tags\" for=\"node\" attr.name=\"tags\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"degree\" for=\"node\" attr.name=\"degree\" attr.type=\"int\"/>\n");
+    out.push_str("  <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"count\" for=\"edge\" attr.name=\"count\" attr.type=\"int\"/>\n");
+    out.push_str("  <graph id=\"notes\" edgedefault=\"directed\">\n");
+
+    for node in &graph.nodes {
+        out.push_str(&format!("    <node id=\"{}\">\n", escape_xml(&node.id)));
+        out.push_str(&format!("      <data key=\"title\">{}</data>\n", escape_xml(&node.title)));
+        out.push_str(&format!(
+            "      <data key=\"tags\">{}</data>\n",
+            escape_xml(&node.tags.join(","))
+        ));
+        out.push_str(&format!("      <data key=\"degree\">{}</data>\n", node.degree));
+        out.push_str("    </node>\n");
+    }
+
+    for (i, edge) in graph.edges.iter().enumerate() {
+        out.push_str(&format!(
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">\n",
+            i,
+            escape_xml(&edge.source),
+            escape_xml(&edge.target)
+        ));
+        out.push_str(&format!("      <data key=\"kind\">{}</data>\n", kind_name(edge.kind)));
+        out.push_str(&format!("      <data key=\"count\">{}</data>\n", edge.count));
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n");
+    out.push_str("</graphml>\n");
+    out
+}
+
+/// Serialize the graph in Graphviz DOT format; embeds are drawn dashed.
+pub fn to_dot(graph: &NoteGraph) -> String {
+    let mut out = String::from("digraph notes {\n");
+
+    for node in &graph.nodes {
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\"];\n",
+            escape_dot(&node.id),
+            escape_dot(&node.title)
+        ));
+    }
+
+    for edge in &graph.edges {
+        let style = if edge.kind == LinkKind::Embed {
+            ", style=dashed"
+        } else {
+            ""
+        };
+        out.push_str(&format!(
+            "  \"{}\" -> \"{}\" [kind=\"{}\", weight={}{}];\n",
+            escape_dot(&edge.source),
+            escape_dot(&edge.target),
+            kind_name(edge.kind),
+            edge.count,
+            style
+        ));
+    }
+
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,271 @@
+//! Image format detection and size-limited re-encoding for imported images.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, ImageEncoder, ImageReader};
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Smallest edge length images are scaled down to when fitting a size limit.
+const MIN_DIMENSION: u32 = 256;
+
+/// Bounds for the requested thumbnail size.
+const THUMBNAIL_MIN_PX: u32 = 16;
+const THUMBNAIL_MAX_PX: u32 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    WebP,
+    Svg,
+    Heic,
+}
+
+impl ImageFormat {
+    /// Canonical file extension (without the dot).
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Gif => "gif",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Svg => "svg",
+            ImageFormat::Heic => "heic",
+        }
+    }
+
+    /// Whether `ext` is an accepted spelling of this format's extension.
+    pub fn accepts_extension(self, ext: &str) -> bool {
+        let ext = ext.to_ascii_lowercase();
+        match self {
+            ImageFormat::Jpeg => ext == "jpg" || ext == "jpeg",
+            ImageFormat::Heic => ext == "heic" || ext == "heif",
+            other => ext == other.extension(),
+        }
+    }
+}
+
+/// How many leading bytes `sniff_format` looks at.
+pub const SNIFF_LEN: usize = 4096;
+
+/// Detect the image format from the leading bytes. Returns None for anything
+/// that isn't one of the supported image formats.
+pub fn sniff_format(data: &[u8]) -> Option<ImageFormat> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(ImageFormat::Png);
+    }
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(ImageFormat::Jpeg);
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some(ImageFormat::Gif);
+    }
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some(ImageFormat::WebP);
+    }
+    // ISO base media file with a HEIF brand: [size]ftyp[brand]
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        let brand = &data[8..12];
+        if [b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1"]
+            .iter()
+            .any(|b| brand == *b)
+        {
+            return Some(ImageFormat::Heic);
+        }
+    }
+    if is_svg(data) {
+        return Some(ImageFormat::Svg);
+    }
+    None
+}
+
+/// SVG is text: allow a BOM, XML declaration, comments and doctype before `<svg`.
+fn is_svg(data: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&data[..data.len().min(SNIFF_LEN)]);
+    let mut rest = head.trim_start_matches('\u{FEFF}').trim_start();
+    loop {
+        if rest.starts_with("<svg") {
+            return true;
+        }
+        let skip_until = if rest.starts_with("<?") {
+            "?>"
+        } else if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<!DOCTYPE") || rest.starts_with("<!doctype") {
+            ">"
+        } else {
+            return false;
+        };
+        match rest.find(skip_until) {
+            Some(end) => rest = rest[end + skip_until.len()..].trim_start(),
+            None => return false,
+        }
+    }
+}
+
+/// Re-encode a raster image so it fits in `max_bytes`: JPEG for opaque images,
+/// PNG when there is transparency, scaling down until it fits. Returns None if
+/// the image can't be decoded or can't be made small enough.
+pub fn fit_to_size(data: &[u8], max_bytes: usize) -> Option<(Vec<u8>, ImageFormat)> {
+    let decoded = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()?;
+    let has_alpha = decoded.color().has_alpha();
+    let mut current = decoded;
+
+    loop {
+        let (encoded, format) = if has_alpha {
+            (encode_png(&current)?, ImageFormat::Png)
+        } else {
+            (encode_jpeg(&current, 85)?, ImageFormat::Jpeg)
+        };
+        if encoded.len() <= max_bytes {
+            return Some((encoded, format));
+        }
+        let (width, height) = (current.width(), current.height());
+        if width.min(height) <= MIN_DIMENSION {
+            return None;
+        }
+        current = current.resize(
+            width * 3 / 4,
+            height * 3 / 4,
+            image::imageops::FilterType::Triangle,
+        );
+    }
+}
+
+pub fn encode_png(image: &DynamicImage) -> Option<Vec<u8>> {
+    let rgba = image.to_rgba8();
+    let mut out = Vec::new();
+    PngEncoder::new(&mut out)
+        .write_image(&rgba, rgba.width(), rgba.height(), image::ExtendedColorType::Rgba8)
+        .ok()?;
+    Some(out)
+}
+
+pub fn encode_jpeg(image: &DynamicImage, quality: u8) -> Option<Vec<u8>> {
+    let rgb = image.to_rgb8();
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, quality)
+        .write_image(&rgb, rgb.width(), rgb.height(), image::ExtendedColorType::Rgb8)
+        .ok()?;
+    Some(out)
+}
+
+/// Content hashes of images, reused while a file's size and modification time
+/// stay the same, so thumbnails are found without reading the image again.
+#[derive(Debug, Default)]
+pub struct HashMemo {
+    entries: HashMap<PathBuf, (u64, Option<SystemTime>, String)>,
+}
+
+impl HashMemo {
+    fn get(&self, path: &Path, metadata: &std::fs::Metadata) -> Option<String> {
+        let (size, modified, hash) = self.entries.get(path)?;
+        (*size == metadata.len() && *modified == metadata.modified().ok()).then(|| hash.clone())
+    }
+
+    fn insert(&mut self, path: &Path, metadata: &std::fs::Metadata, hash: String) {
+        self.entries
+            .insert(path.to_path_buf(), (metadata.len(), metadata.modified().ok(), hash));
+    }
+}
+
+/// Content hash of the file at `path`, from `memo` while the file is unchanged.
+pub fn memo_hash(memo: &Mutex<HashMemo>, path: &Path) -> std::io::Result<String> {
+    let metadata = std::fs::metadata(path)?;
+    if let Some(hash) = memo.lock().expect("hash memo mutex").get(path, &metadata) {
+        return Ok(hash);
+    }
+    let hash = crate::assets::hash_file(path)?;
+    memo.lock().expect("hash memo mutex").insert(path, &metadata, hash.clone());
+    Ok(hash)
+}
+
+/// An existing thumbnail (either format) of the image with this hash.
+fn cached_thumbnail(cache_dir: &Path, hash: &str, max_px: u32) -> Option<PathBuf> {
+    ["jpg", "png"]
+        .iter()
+        .map(|extension| cache_dir.join(format!("{}-{}.{}", hash, max_px, extension)))
+        .find(|cached| cached.exists())
+}
+
+/// Path of a thumbnail of `source` no larger than `max_px` on either side,
+/// generated into `cache_dir` on first use and keyed by content hash and size.
+/// The hash comes from `memo` when the image is unchanged, so a cached
+/// thumbnail is found without reading the image.
+/// Vector images, undecodable formats (e.g. HEIC) and images that are already
+/// small enough are returned as-is.
+pub fn thumbnail(source: &Path, cache_dir: &Path, max_px: u32, memo: &Mutex<HashMemo>) -> Result<PathBuf, String> {
+    let max_px = max_px.clamp(THUMBNAIL_MIN_PX, THUMBNAIL_MAX_PX);
+    let metadata = std::fs::metadata(source).map_err(|e| format!("Failed to read image: {}", e))?;
+    let known = memo.lock().expect("hash memo mutex").get(source, &metadata);
+    if let Some(cached) = known.as_deref().and_then(|hash| cached_thumbnail(cache_dir, hash, max_px)) {
+        return Ok(cached);
+    }
+
+    let data = std::fs::read(source).map_err(|e| format!("Failed to read image: {}", e))?;
+    let hash = crate::assets::content_hash(&data);
+    memo.lock().expect("hash memo mutex").insert(source, &metadata, hash.clone());
+    if known.as_deref() != Some(hash.as_str()) {
+        if let Some(cached) = cached_thumbnail(cache_dir, &hash, max_px) {
+            return Ok(cached);
+        }
+    }
+
+    match sniff_format(&data) {
+        None => return Err("Not a supported image".to_string()),
+        Some(ImageFormat::Svg | ImageFormat::Heic) => return Ok(source.to_path_buf()),
+        Some(_) => {}
+    }
+    let Some(decoded) = ImageReader::new(Cursor::new(&data))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.decode().ok())
+    else {
+        return Ok(source.to_path_buf());
+    };
+    if decoded.width() <= max_px && decoded.height() <= max_px {
+        return Ok(source.to_path_buf());
+    }
+
+    let resized = decoded.thumbnail(max_px, max_px);
+    let (encoded, extension) = if resized.color().has_alpha() {
+        (encode_png(&resized), "png")
+    } else {
+        (encode_jpeg(&resized, 85), "jpg")
+    };
+    let encoded = encoded.ok_or("Failed to encode thumbnail")?;
+
+    std::fs::create_dir_all(cache_dir).map_err(|e| e.to_string())?;
+    let target = cache_dir.join(format!("{}-{}.{}", hash, max_px, extension));
+    crate::write_file_atomic(&target, &encoded).map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+    Ok(target)
+}
+
+/// Delete the thumbnails in `cache_dir` of images whose hash isn't in `keep`.
+/// Returns how many were deleted.
+pub fn prune_thumbnails(cache_dir: &Path, keep: &HashSet<String>) -> std::io::Result<usize> {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some((hash, _)) = name.to_str().and_then(|name| name.split_once('-')) else {
+            continue;
+        };
+        if !keep.contains(hash) {
+            std::fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 use base64::Engine;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
@@ -14,7 +14,30 @@ use tauri::{AppHandle, Emitter, Manager, State, WebviewUrl};
 use tauri::webview::WebviewWindowBuilder;
 use tauri_plugin_clipboard_manager::ClipboardExt;
 use tokio::fs;
-
+use tokio::io::AsyncReadExt;
+
+mod assets;
+mod attachments;
+mod cjk;
+mod daily;
+mod encoding;
+mod export;
+mod format;
+mod graph;
+mod images;
+mod link_check;
+mod line_endings;
+mod links;
+mod markdown;
+mod naming;
+mod outline;
+mod preview;
+mod stats;
+mod tags;
+mod tasks;
+mod templates;
+
+use preview::{extract_title, generate_preview};
 
 // Note metadata for list display
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +56,18 @@ pub struct Note {
     pub content: String,
     pub path: String,
     pub modified: i64,
+    // Character encoding of the file on disk (e.g. "UTF-8", "GBK") and whether it has a BOM
+    pub encoding: String,
+    pub bom: bool,
+    // Dominant line ending of the file ("lf" or "crlf")
+    #[serde(rename = "lineEnding")]
+    pub line_ending: line_endings::LineEnding,
+    // Notes whose links couldn't be updated after this note was renamed, with the reason
+    #[serde(rename = "linkRewriteErrors", default, skip_serializing_if = "Vec::is_empty")]
+    pub link_rewrite_errors: Vec<String>,
+    // Set when the file has bytes its encoding can't decode, so saving would lose them
+    #[serde(rename = "readOnly")]
+    pub read_only: bool,
 }
 
 // Theme color customization
@@ -97,6 +132,37 @@ pub struct Settings {
     #[serde(rename = "editorWidth")]
     pub editor_width: Option<String>,
     pub language: Option<String>,
+    // Pasted images larger than this many bytes are re-encoded to fit
+    #[serde(rename = "imageSizeLimit")]
+    pub image_size_limit: Option<u64>,
+    // Where imported files are stored and how they are named
+    pub attachments: Option<attachments::AttachmentSettings>,
+    // Imported files larger than this many bytes are rejected
+    #[serde(rename = "attachmentSizeLimit")]
+    pub attachment_size_limit: Option<u64>,
+    #[serde(rename = "dailyNotes")]
+    pub daily_notes: Option<daily::DailyNoteSettings>,
+    // Extra file extensions treated as notes besides .md and .markdown (e.g. "mdx", "txt")
+    #[serde(rename = "noteExtensions")]
+    pub note_extensions: Option<Vec<String>>,
+    // Run the markdown formatter on every save
+    #[serde(rename = "formatOnSave")]
+    pub format_on_save: Option<bool>,
+    #[serde(rename = "formatOptions")]
+    pub format_options: Option<format::FormatOptions>,
+    // Settings that only apply to one notes folder, keyed by folder path
+    #[serde(default)]
+    pub folders: HashMap<String, FolderSettings>,
+}
+
+// Per-folder settings
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FolderSettings {
+    #[serde(rename = "filenameStrategy")]
+    pub filename_strategy: Option<naming::FilenameStrategy>,
+    // Force this line ending on save instead of keeping each file's own
+    #[serde(rename = "lineEnding")]
+    pub line_ending: Option<line_endings::LineEnding>,
 }
 
 // Search result
@@ -135,8 +201,16 @@ pub struct SearchIndex {
     title_field: Field,
     content_field: Field,
     modified_field: Field,
+    // File names of attachments linked from the note
+    attachments_field: Field,
 }
 
+/// Version of the search index schema, bumped whenever its fields change.
+/// Version 1 indexes predate the version file.
+const SEARCH_SCHEMA_VERSION: u32 = 2;
+/// File in the index directory recording the schema version it was built with
+const SEARCH_SCHEMA_VERSION_FILE: &str = "verso-schema-version";
+
 impl SearchIndex {
     fn new(index_path: &PathBuf) -> Result<Self> {
         // Build schema
@@ -145,12 +219,48 @@ impl SearchIndex {
         let title_field = schema_builder.add_text_field("title", TEXT | STORED);
         let content_field = schema_builder.add_text_field("content", TEXT | STORED);
         let modified_field = schema_builder.add_i64_field("modified", INDEXED | STORED);
+        let attachments_field = schema_builder.add_text_field("attachments", TEXT);
         let schema = schema_builder.build();
 
-        // Create or open index
+        // Create or open index. It only caches the notes, so an index with an older
+        // schema (or one that can't be opened) is rebuilt, but one written by a newer
+        // version of the app is left for that version
         std::fs::create_dir_all(index_path)?;
-        let index = Index::create_in_dir(index_path, schema.clone())
-            .or_else(|_| Index::open_in_dir(index_path))?;
+        let version_path = index_path.join(SEARCH_SCHEMA_VERSION_FILE);
+        let stored_version = std::fs::read_to_string(&version_path)
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok());
+        if let Some(version) = stored_version.filter(|v| *v > SEARCH_SCHEMA_VERSION) {
+            return Err(anyhow::anyhow!(
+                "Search index at {} has schema version {}, newer than this app's {}",
+                index_path.display(),
+                version,
+                SEARCH_SCHEMA_VERSION
+            ));
+        }
+        let index = if !index_path.join("meta.json").exists() {
+            Index::create_in_dir(index_path, schema.clone())?
+        } else {
+            match Index::open_in_dir(index_path) {
+                Ok(index) if index.schema() == schema => index,
+                opened => {
+                    let reason = match opened {
+                        Ok(_) => format!(
+                            "schema version {} is out of date",
+                            stored_version.map_or("1".to_string(), |v| v.to_string())
+                        ),
+                        Err(e) => format!("it can't be opened: {}", e),
+                    };
+                    eprintln!("Rebuilding search index at {}: {}", index_path.display(), reason);
+                    std::fs::remove_dir_all(index_path)?;
+                    std::fs::create_dir_all(index_path)?;
+                    Index::create_in_dir(index_path, schema.clone())?
+                }
+            }
+        };
+        if stored_version != Some(SEARCH_SCHEMA_VERSION) {
+            std::fs::write(&version_path, SEARCH_SCHEMA_VERSION.to_string())?;
+        }
 
         let reader = index
             .reader_builder()
@@ -168,10 +278,11 @@ impl SearchIndex {
             title_field,
             content_field,
             modified_field,
+            attachments_field,
         })
     }
 
-    fn index_note(&self, id: &str, title: &str, content: &str, modified: i64) -> Result<()> {
+    fn index_note(&self, id: &str, title: &str, content: &str, modified: i64, layout: &NoteLayout) -> Result<()> {
         let mut writer = self.writer.lock().expect("search writer mutex");
 
         // Delete existing document with this ID
@@ -184,6 +295,7 @@ impl SearchIndex {
             self.title_field => title,
             self.content_field => content,
             self.modified_field => modified,
+            self.attachments_field => assets::attachment_names(content, layout).join(" "),
         ))?;
 
         writer.commit()?;
@@ -201,7 +313,7 @@ impl SearchIndex {
     fn search(&self, query_str: &str, limit: usize) -> Result<Vec<SearchResult>> {
         let searcher = self.reader.searcher();
         let query_parser =
-            QueryParser::for_index(&self.index, vec![self.title_field, self.content_field]);
+            QueryParser::for_index(&self.index, vec![self.title_field, self.content_field, self.attachments_field]);
 
         // Parse query, fall back to prefix query if parsing fails
         let query = query_parser
@@ -250,7 +362,7 @@ impl SearchIndex {
         Ok(results)
     }
 
-    fn rebuild_index(&self, notes_folder: &PathBuf) -> Result<()> {
+    fn rebuild_index(&self, notes_folder: &PathBuf, layout: &NoteLayout) -> Result<()> {
         let mut writer = self.writer.lock().expect("search writer mutex");
         writer.delete_all_documents()?;
 
@@ -259,15 +371,15 @@ impl SearchIndex {
             for entry in WalkDir::new(notes_folder)
                 .max_depth(1)
                 .into_iter()
-                .filter_entry(is_visible_notes_entry)
+                .filter_entry(|e| is_visible_notes_entry(e, layout))
                 .flatten()
             {
                 let file_path = entry.path();
                 if !file_path.is_file() {
                     continue;
                 }
-                if let Some(id) = id_from_abs_path(notes_folder, file_path) {
-                    if let Ok(content) = std::fs::read_to_string(file_path) {
+                if let Some(id) = id_from_abs_path(notes_folder, file_path, layout) {
+                    if let Ok(content) = encoding::read_to_string(file_path) {
                         let modified = entry
                             .metadata()
                             .ok()
@@ -283,6 +395,7 @@ impl SearchIndex {
                             self.title_field => title,
                             self.content_field => content.as_str(),
                             self.modified_field => modified,
+                            self.attachments_field => assets::attachment_names(&content, layout).join(" "),
                         ))?;
                     }
                 }
@@ -300,7 +413,14 @@ pub struct FolderState {
     pub notes_cache: RwLock<HashMap<String, NoteMetadata>>,
     pub file_watcher: Mutex<Option<FileWatcherState>>,
     pub search_index: Mutex<Option<SearchIndex>>,
+    pub tag_index: RwLock<tags::TagIndex>,
+    pub outline_index: RwLock<outline::OutlineIndex>,
+    pub task_index: RwLock<tasks::TaskIndex>,
+    pub stats_index: RwLock<stats::StatsIndex>,
+    pub link_graph: RwLock<links::LinkGraph>,
     pub debounce_map: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+    // Content hashes of images, so thumbnails are looked up without re-reading them
+    pub image_hashes: Arc<Mutex<images::HashMemo>>,
 }
 
 // App state: global config + per-folder states
@@ -311,6 +431,61 @@ pub struct AppState {
     pub folder_states: RwLock<HashMap<String, Arc<FolderState>>>,
 }
 
+/// Which files in a notes folder are notes, as configured in settings.
+/// Built from `AppState.settings` by each command and passed down to the
+/// path helpers, so they always follow the current settings.
+#[derive(Debug, Clone)]
+pub struct NoteLayout {
+    /// Note extensions in lookup priority order (`md` first), lowercase
+    extensions: Vec<String>,
+    attachments: attachments::AttachmentSettings,
+    /// Top-level folders holding attachments, never scanned for notes
+    attachment_dirs: Vec<String>,
+}
+
+impl NoteLayout {
+    fn new(settings: &Settings) -> Self {
+        let attachments = settings.attachments.clone().unwrap_or_default();
+        Self {
+            extensions: naming::note_extensions(settings.note_extensions.as_deref().unwrap_or_default()),
+            attachment_dirs: attachments.hidden_dirs(),
+            attachments,
+        }
+    }
+
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    pub fn attachments(&self) -> &attachments::AttachmentSettings {
+        &self.attachments
+    }
+
+    /// Whether a folder (relative to the notes root, POSIX separators) holds attachments.
+    /// `assets` folders are skipped at any depth, a custom folder only at its own path.
+    pub fn is_attachment_dir(&self, relative_dir: &str) -> bool {
+        let name = relative_dir.rsplit('/').next().unwrap_or(relative_dir);
+        name == assets::ASSETS_DIR || self.attachment_dirs.iter().any(|d| d == relative_dir)
+    }
+
+    /// Whether files with this extension (without the dot, any case) are notes.
+    pub fn is_note_extension(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension))
+    }
+
+    /// Whether a path has a note extension.
+    pub fn is_note_file(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.is_note_extension(ext))
+    }
+}
+
+// Helper: the note layout for the current settings
+fn note_layout(state: &AppState) -> NoteLayout {
+    NoteLayout::new(&state.settings.read().expect("settings read lock"))
+}
+
 // Helper: get the Arc<FolderState> for a given folder path
 fn get_folder_state(state: &AppState, folder: &str) -> Result<Arc<FolderState>, String> {
     let states = state.folder_states.read().expect("folder_states read lock");
@@ -320,6 +495,61 @@ fn get_folder_state(state: &AppState, folder: &str) -> Result<Arc<FolderState>,
         .ok_or_else(|| format!("Folder not initialized: {}", folder))
 }
 
+// Update every per-folder index after a note was written
+fn index_note_content(fs: &FolderState, layout: &NoteLayout, id: &str, title: &str, content: &str, modified: i64) {
+    {
+        let index = fs.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.index_note(id, title, content, modified, layout);
+        }
+    }
+
+    fs.tag_index.write().expect("tag index write lock").update(id, content);
+    fs.outline_index.write().expect("outline index write lock").update(id, content);
+    fs.task_index.write().expect("task index write lock").update(id, content);
+    fs.stats_index.write().expect("stats index write lock").update(id, content);
+    fs.link_graph.write().expect("link graph write lock").update(id, title, content);
+}
+
+// Drop a note from every per-folder index
+fn unindex_note(fs: &FolderState, id: &str) {
+    {
+        let index = fs.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.delete_note(id);
+        }
+    }
+
+    fs.tag_index.write().expect("tag index write lock").remove(id);
+    fs.outline_index.write().expect("outline index write lock").remove(id);
+    fs.task_index.write().expect("task index write lock").remove(id);
+    fs.stats_index.write().expect("stats index write lock").remove(id);
+    fs.link_graph.write().expect("link graph write lock").remove(id);
+}
+
+// Rebuild the in-memory note indexes (tags, outlines, tasks, stats, links) from the files on disk
+fn rebuild_note_indexes(fs: &FolderState, notes_root: &Path, layout: &NoteLayout) {
+    let mut tag_index = fs.tag_index.write().expect("tag index write lock");
+    let mut outline_index = fs.outline_index.write().expect("outline index write lock");
+    let mut task_index = fs.task_index.write().expect("task index write lock");
+    let mut stats_index = fs.stats_index.write().expect("stats index write lock");
+    let mut link_graph = fs.link_graph.write().expect("link graph write lock");
+    tag_index.clear();
+    outline_index.clear();
+    task_index.clear();
+    stats_index.clear();
+    link_graph.clear();
+    for (id, file_path) in list_note_files(notes_root, layout) {
+        if let Ok(content) = encoding::read_to_string(&file_path) {
+            tag_index.update(&id, &content);
+            outline_index.update(&id, &content);
+            task_index.update(&id, &content);
+            stats_index.update(&id, &content);
+            link_graph.update(&id, &extract_title(&content), &content);
+        }
+    }
+}
+
 // Utility: Sanitize filename from title
 fn sanitize_filename(title: &str) -> String {
     let sanitized: String = title
@@ -339,7 +569,6 @@ fn sanitize_filename(title: &str) -> String {
     }
 }
 
-/// Expands template tags in a note name template using local timezone
 /// Extracts a display title from a note ID (filename)
 fn extract_title_from_id(id: &str) -> String {
     // Get last path component (filename)
@@ -368,207 +597,118 @@ fn is_effectively_empty(s: &str) -> bool {
         .all(|c| c.is_whitespace() || c == '\u{00A0}' || c == '\u{FEFF}')
 }
 
-/// Strip YAML frontmatter (leading `---` ... `---` block) from content.
-fn strip_frontmatter(content: &str) -> &str {
-    let trimmed = content.trim_start();
-    if trimmed.starts_with("---") {
-        // Find the closing --- (skip the opening line)
-        if let Some(rest) = trimmed.strip_prefix("---") {
-            if let Some(end) = rest.find("\n---") {
-                // Skip past closing --- and the newline after it (handle CRLF)
-                let after_close = &rest[end + 4..];
-                return after_close
-                    .strip_prefix("\r\n")
-                    .or_else(|| after_close.strip_prefix('\n'))
-                    .unwrap_or(after_close);
-            }
-        }
-    }
-    content
-}
-
-// Utility: Extract title from markdown content
-fn extract_title(content: &str) -> String {
-    let body = strip_frontmatter(content);
-    for line in body.lines() {
-        let trimmed = line.trim();
-        if let Some(title) = trimmed.strip_prefix("# ") {
-            let title = title.trim();
-            if !is_effectively_empty(title) {
-                return title.to_string();
-            }
-        }
-        if !is_effectively_empty(trimmed) {
-            return trimmed.chars().take(50).collect();
-        }
-    }
-    "Untitled".to_string()
-}
-
-// Utility: Generate preview from content (strip markdown formatting)
-fn generate_preview(content: &str) -> String {
-    let body = strip_frontmatter(content);
-    let mut preview = String::new();
-    // Skip the first line (title), collect multiple lines for richer preview
-    for line in body.lines().skip(1) {
-        let trimmed = line.trim();
-        if !trimmed.is_empty() {
-            let stripped = strip_markdown(trimmed);
-            if !stripped.is_empty() {
-                if !preview.is_empty() {
-                    preview.push(' ');
-                }
-                preview.push_str(&stripped);
-                if preview.len() >= 200 {
-                    break;
-                }
-            }
-        }
-    }
-    preview.chars().take(200).collect()
-}
-
-// Strip common markdown formatting from text
-fn strip_markdown(text: &str) -> String {
-    let mut result = text.to_string();
-
-    // Remove heading markers (##, ###, etc.)
-    let trimmed = result.trim_start();
-    if trimmed.starts_with('#') {
-        result = trimmed.trim_start_matches('#').trim_start().to_string();
-    }
-
-    // Remove strikethrough (~~text~~) - before other markers
-    while let Some(start) = result.find("~~") {
-        if let Some(end) = result[start + 2..].find("~~") {
-            let inner = &result[start + 2..start + 2 + end];
-            result = format!("{}{}{}", &result[..start], inner, &result[start + 4 + end..]);
-        } else {
-            break;
-        }
-    }
-
-    // Remove bold (**text** or __text__) - before italic
-    while let Some(start) = result.find("**") {
-        if let Some(end) = result[start + 2..].find("**") {
-            let inner = &result[start + 2..start + 2 + end];
-            result = format!("{}{}{}", &result[..start], inner, &result[start + 4 + end..]);
-        } else {
-            break;
-        }
-    }
-    while let Some(start) = result.find("__") {
-        if let Some(end) = result[start + 2..].find("__") {
-            let inner = &result[start + 2..start + 2 + end];
-            result = format!("{}{}{}", &result[..start], inner, &result[start + 4 + end..]);
-        } else {
-            break;
-        }
-    }
-
-    // Remove inline code (`code`)
-    while let Some(start) = result.find('`') {
-        if let Some(end) = result[start + 1..].find('`') {
-            let inner = &result[start + 1..start + 1 + end];
-            result = format!("{}{}{}", &result[..start], inner, &result[start + 2 + end..]);
-        } else {
-            break;
-        }
-    }
-
-    // Remove images ![alt](url) - must come before links
-    let img_re = regex::Regex::new(r"!\[([^\]]*)\]\([^)]+\)").unwrap();
-    result = img_re.replace_all(&result, "$1").to_string();
-
-    // Remove links [text](url)
-    let link_re = regex::Regex::new(r"\[([^\]]+)\]\([^)]+\)").unwrap();
-    result = link_re.replace_all(&result, "$1").to_string();
-
-    // Remove italic (*text* or _text_) - simple approach after bold is removed
-    // Match *text* where text doesn't contain *
-    while let Some(start) = result.find('*') {
-        if let Some(end) = result[start + 1..].find('*') {
-            if end > 0 {
-                let inner = &result[start + 1..start + 1 + end];
-                result = format!("{}{}{}", &result[..start], inner, &result[start + 2 + end..]);
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-    // Match _text_ where text doesn't contain _
-    while let Some(start) = result.find('_') {
-        if let Some(end) = result[start + 1..].find('_') {
-            if end > 0 {
-                let inner = &result[start + 1..start + 1 + end];
-                result = format!("{}{}{}", &result[..start], inner, &result[start + 2 + end..]);
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-
-    // Remove task list markers
-    result = result
-        .replace("- [ ] ", "")
-        .replace("- [x] ", "")
-        .replace("- [X] ", "");
-
-    // Remove list markers at start (-, *, +, 1.)
-    let list_re = regex::Regex::new(r"^(\s*[-+*]|\s*\d+\.)\s+").unwrap();
-    result = list_re.replace(&result, "").to_string();
-
-    result.trim().to_string()
-}
-
-/// Filter for WalkDir: skips dot-directories (e.g. .git) and assets/.
-fn is_visible_notes_entry(entry: &walkdir::DirEntry) -> bool {
+/// Filter for WalkDir: skips dot-directories (e.g. .git) and attachment folders.
+fn is_visible_notes_entry(entry: &walkdir::DirEntry, layout: &NoteLayout) -> bool {
     if entry.file_type().is_dir() {
         let name = entry.file_name().to_str().unwrap_or("");
-        return !name.starts_with('.') && name != "assets";
+        if name.starts_with('.') {
+            return false;
+        }
+        // Path below the walk root, from the last `depth` components
+        let mut segments: Vec<&str> = entry
+            .path()
+            .components()
+            .rev()
+            .take(entry.depth())
+            .filter_map(|c| c.as_os_str().to_str())
+            .collect();
+        segments.reverse();
+        return !layout.is_attachment_dir(&segments.join("/"));
     }
     true
 }
 
-/// Convert an absolute file path to a note ID (relative path from notes root, no .md extension, POSIX separators).
-/// Returns None if the path is outside the root, not a .md file, or in an excluded directory.
-fn id_from_abs_path(notes_root: &Path, file_path: &Path) -> Option<String> {
+/// Convert an absolute file path to a note ID (relative path from notes root, no note extension, POSIX separators).
+/// Returns None if the path is outside the root, not a note file, in an excluded directory,
+/// or shadowed by a file with the same name and a higher-priority extension (`a.md` wins over `a.markdown`).
+fn id_from_abs_path(notes_root: &Path, file_path: &Path, layout: &NoteLayout) -> Option<String> {
     let rel = file_path.strip_prefix(notes_root).ok()?;
 
     // Skip excluded directories (dot-dirs catch .git, etc.)
+    let mut dir = String::new();
     for component in rel.components() {
         if let std::path::Component::Normal(name) = component {
             let name_str = name.to_str()?;
-            if name_str.starts_with('.') || name_str == "assets" {
+            if !dir.is_empty() {
+                dir.push('/');
+            }
+            dir.push_str(name_str);
+            if name_str.starts_with('.') || layout.is_attachment_dir(&dir) {
                 return None;
             }
         }
     }
 
-    // Must be a .md file
-    if file_path.extension()?.to_str()? != "md" {
+    // Must be a note file (.md, .markdown or an extension enabled in settings)
+    let extension = file_path.extension()?.to_str()?;
+    if !layout.is_note_extension(extension) {
         return None;
     }
 
-    // Build ID: relative path without .md suffix, using POSIX separators.
-    // Strip .md by converting to string and trimming (avoids with_extension
+    // Build ID: relative path without the extension, using POSIX separators.
+    // Strip it by converting to string and trimming (avoids with_extension
     // which breaks on stems containing dots like "meeting.2024-01-15.md").
     let rel_str = rel.to_str()?;
-    let id = rel_str.strip_suffix(".md")?.replace(std::path::MAIN_SEPARATOR, "/");
+    let id = rel_str
+        .strip_suffix(extension)?
+        .strip_suffix('.')?
+        .replace(std::path::MAIN_SEPARATOR, "/");
 
     if id.is_empty() {
-        None
-    } else {
-        Some(id)
+        return None;
+    }
+
+    // The same ID with a higher-priority extension takes precedence
+    for preferred in layout.extensions().iter().take_while(|ext| !ext.eq_ignore_ascii_case(extension)) {
+        if find_with_extension(notes_root, &id, preferred).is_some() {
+            return None;
+        }
     }
+
+    Some(id)
 }
 
 /// Convert a note ID to an absolute file path. Validates against path traversal.
-fn abs_path_from_id(notes_root: &Path, id: &str) -> Result<PathBuf, String> {
+/// Uses whichever note extension exists on disk, `.md` for notes that don't exist yet.
+fn abs_path_from_id(notes_root: &Path, id: &str, layout: &NoteLayout) -> Result<PathBuf, String> {
+    validate_note_id(notes_root, id)?;
+
+    Ok(layout
+        .extensions()
+        .iter()
+        .find_map(|ext| find_with_extension(notes_root, id, ext))
+        .unwrap_or_else(|| note_path_with_extension(notes_root, id, naming::DEFAULT_EXTENSION)))
+}
+
+/// The existing file for `id` with this extension, matching the extension in
+/// any case (`Foo.MD` for `md`).
+fn find_with_extension(notes_root: &Path, id: &str, extension: &str) -> Option<PathBuf> {
+    let exact = note_path_with_extension(notes_root, id, extension);
+    if exact.is_file() {
+        return Some(exact);
+    }
+    let leaf = format!("{}.", id.rsplit('/').next().unwrap_or(id));
+    std::fs::read_dir(exact.parent()?)
+        .ok()?
+        .flatten()
+        .find(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.len() == leaf.len() + extension.len()
+                && name.starts_with(&leaf)
+                && name[leaf.len()..].eq_ignore_ascii_case(extension)
+                && entry.path().is_file()
+        })
+        .map(|entry| entry.path())
+}
+
+/// Like abs_path_from_id, but with the given extension (e.g. to keep a renamed note's extension).
+fn abs_path_with_extension(notes_root: &Path, id: &str, extension: &str) -> Result<PathBuf, String> {
+    validate_note_id(notes_root, id)?;
+    Ok(note_path_with_extension(notes_root, id, extension))
+}
+
+fn validate_note_id(notes_root: &Path, id: &str) -> Result<(), String> {
     if id.contains('\\') {
         return Err("Invalid note ID: backslashes not allowed".to_string());
     }
@@ -590,18 +730,73 @@ fn abs_path_from_id(notes_root: &Path, id: &str) -> Result<PathBuf, String> {
         }
     }
 
-    // Append ".md" via OsString to avoid with_extension replacing dots in stems
-    // (e.g. "meeting.2024-01-15" would become "meeting.md" with with_extension)
-    let joined = notes_root.join(rel);
-    let mut file_path_os = joined.into_os_string();
-    file_path_os.push(".md");
-    let file_path = PathBuf::from(file_path_os);
-
-    if !file_path.starts_with(notes_root) {
+    if !notes_root.join(rel).starts_with(notes_root) {
         return Err("Invalid note ID: path escapes notes folder".to_string());
     }
 
-    Ok(file_path)
+    Ok(())
+}
+
+// Append the extension via OsString to avoid with_extension replacing dots in stems
+// (e.g. "meeting.2024-01-15" would become "meeting.md" with with_extension)
+fn note_path_with_extension(notes_root: &Path, id: &str, extension: &str) -> PathBuf {
+    let mut file_path_os = notes_root.join(id).into_os_string();
+    file_path_os.push(".");
+    file_path_os.push(extension);
+    PathBuf::from(file_path_os)
+}
+
+/// List (note ID, absolute path) for every visible note in the folder (top-level only).
+fn list_note_files(notes_root: &Path, layout: &NoteLayout) -> Vec<(String, PathBuf)> {
+    use walkdir::WalkDir;
+    let mut files = Vec::new();
+    if !notes_root.exists() {
+        return files;
+    }
+    for entry in WalkDir::new(notes_root)
+        .max_depth(1)
+        .into_iter()
+        .filter_entry(|e| is_visible_notes_entry(e, layout))
+        .flatten()
+    {
+        let file_path = entry.path();
+        if !file_path.is_file() {
+            continue;
+        }
+        if let Some(id) = id_from_abs_path(notes_root, file_path, layout) {
+            files.push((id, file_path.to_path_buf()));
+        }
+    }
+    files
+}
+
+/// Write a file via a temporary sibling and rename, so readers never see a partial file.
+fn write_file_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.verso-tmp", file_name));
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp_path);
+    })
+}
+
+/// Atomically overwrite a note, keeping the file's character encoding.
+fn write_note_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let (bytes, _) = encoding::encode_for_file(path, content, false)?;
+    write_file_atomic(path, &bytes)
+}
+
+// Get the modification time of a file as unix seconds (0 if unavailable)
+fn file_modified_secs(path: &Path) -> i64 {
+    std::fs::metadata(path)
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 // Get app config file path (in app data directory)
@@ -738,9 +933,10 @@ fn initialize_folder(app: AppHandle, folder: String, state: State<AppState>) ->
     }
 
     // Initialize search index
+    let layout = note_layout(&state);
     let search_index = if let Ok(index_path) = get_search_index_path(&app, &normalized_path) {
         SearchIndex::new(&index_path).ok().inspect(|idx| {
-            let _ = idx.rebuild_index(&path_buf);
+            let _ = idx.rebuild_index(&path_buf, &layout);
         })
     } else {
         None
@@ -752,8 +948,15 @@ fn initialize_folder(app: AppHandle, folder: String, state: State<AppState>) ->
         notes_cache: RwLock::new(HashMap::new()),
         file_watcher: Mutex::new(None),
         search_index: Mutex::new(search_index),
+        tag_index: RwLock::new(tags::TagIndex::default()),
+        outline_index: RwLock::new(outline::OutlineIndex::default()),
+        task_index: RwLock::new(tasks::TaskIndex::default()),
+        stats_index: RwLock::new(stats::StatsIndex::default()),
+        link_graph: RwLock::new(links::LinkGraph::default()),
         debounce_map: Arc::new(Mutex::new(HashMap::new())),
+        image_hashes: Arc::new(Mutex::new(images::HashMemo::default())),
     });
+    rebuild_note_indexes(&folder_state, &path_buf, &layout);
 
     // Register in folder_states
     {
@@ -785,6 +988,7 @@ async fn list_notes(folder: String, state: State<'_, AppState>) -> Result<Vec<No
         return Ok(vec![]);
     }
 
+    let layout = note_layout(&state);
     let path_clone = path.clone();
     let discovered = tokio::task::spawn_blocking(move || {
         use walkdir::WalkDir;
@@ -792,15 +996,15 @@ async fn list_notes(folder: String, state: State<'_, AppState>) -> Result<Vec<No
         for entry in WalkDir::new(&path_clone)
             .max_depth(1)
             .into_iter()
-            .filter_entry(is_visible_notes_entry)
+            .filter_entry(|e| is_visible_notes_entry(e, &layout))
             .flatten()
         {
             let file_path = entry.path();
             if !file_path.is_file() {
                 continue;
             }
-            if let Some(id) = id_from_abs_path(&path_clone, file_path) {
-                if let Ok(content) = std::fs::read_to_string(file_path) {
+            if let Some(id) = id_from_abs_path(&path_clone, file_path, &layout) {
+                if let Ok(content) = encoding::read_to_string(file_path) {
                     let modified = entry
                         .metadata()
                         .ok()
@@ -830,7 +1034,7 @@ async fn list_notes(folder: String, state: State<'_, AppState>) -> Result<Vec<No
         .collect();
 
     // Sort by date (newest first)
-    notes.sort_by(|a, b| b.modified.cmp(&a.modified));
+    notes.sort_by_key(|note| std::cmp::Reverse(note.modified));
 
     // Update cache efficiently
     {
@@ -850,14 +1054,12 @@ async fn read_note(folder: String, id: String, state: State<'_, AppState>) -> Re
     let _fs = get_folder_state(&state, &folder)?;
 
     let folder_path = PathBuf::from(&folder);
-    let file_path = abs_path_from_id(&folder_path, &id)?;
+    let file_path = abs_path_from_id(&folder_path, &id, &note_layout(&state))?;
     if !file_path.exists() {
         return Err("Note not found".to_string());
     }
 
-    let content = fs::read_to_string(&file_path)
-        .await
-        .map_err(|e| e.to_string())?;
+    let (content, detected, malformed) = read_note_file(&file_path).await?;
     let metadata = fs::metadata(&file_path)
         .await
         .map_err(|e| e.to_string())?;
@@ -868,6 +1070,7 @@ async fn read_note(folder: String, id: String, state: State<'_, AppState>) -> Re
         .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
+    let line_ending = line_endings::detect(&content).unwrap_or_default();
 
     Ok(Note {
         id,
@@ -875,6 +1078,219 @@ async fn read_note(folder: String, id: String, state: State<'_, AppState>) -> Re
         content,
         path: file_path.to_string_lossy().into_owned(),
         modified,
+        encoding: detected.name().to_string(),
+        bom: detected.bom,
+        line_ending,
+        link_rewrite_errors: Vec::new(),
+        read_only: malformed,
+    })
+}
+
+// Read a note in whatever encoding it uses, returning the decoded text, the encoding and
+// whether some bytes couldn't be decoded
+async fn read_note_file(file_path: &Path) -> Result<(String, encoding::TextEncoding, bool), String> {
+    let bytes = fs::read(file_path).await.map_err(|e| e.to_string())?;
+    Ok(encoding::decode(&bytes))
+}
+
+// Where save_note writes a note
+struct SaveTarget {
+    id: String,
+    path: PathBuf,
+    // Previous (ID, path) when the save renames the note
+    previous: Option<(String, PathBuf)>,
+}
+
+// Content for a new note, in the folder's forced line ending if it has one
+fn with_folder_line_ending(state: &AppState, folder: &str, content: String) -> String {
+    match folder_settings(state, folder).line_ending {
+        Some(ending) => line_endings::apply(&content, ending),
+        None => content,
+    }
+}
+
+fn folder_settings(state: &AppState, folder: &str) -> FolderSettings {
+    state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .folders
+        .get(folder)
+        .cloned()
+        .unwrap_or_default()
+}
+
+// Pick a note ID for `leaf` in the `dir_prefix` subfolder that no other file uses,
+// adding -1, -2, ... on collision. `current_id` is the note being saved, whose
+// own file doesn't count as a collision.
+fn unique_note_id(
+    folder_path: &Path,
+    dir_prefix: Option<&str>,
+    leaf: &str,
+    current_id: Option<&str>,
+    layout: &NoteLayout,
+) -> String {
+    let with_prefix = |name: String| match dir_prefix {
+        Some(prefix) => format!("{}/{}", prefix, name),
+        None => name,
+    };
+    let mut new_id = with_prefix(leaf.to_string());
+    let mut counter = 1;
+
+    while Some(new_id.as_str()) != current_id
+        && abs_path_from_id(folder_path, &new_id, layout)
+            .map(|p| p.exists())
+            .unwrap_or(false)
+    {
+        new_id = with_prefix(format!("{}-{}", leaf, counter));
+        counter += 1;
+    }
+    new_id
+}
+
+// Determine the file ID and path a note is saved to, handling renames
+fn resolve_save_target(
+    folder_path: &Path,
+    id: Option<String>,
+    content: &str,
+    strategy: naming::FilenameStrategy,
+    layout: &NoteLayout,
+) -> Result<SaveTarget, String> {
+    let title = extract_title(content);
+
+    let Some(existing_id) = id else {
+        // New notes go in root
+        let new_id = unique_note_id(folder_path, None, &strategy.name_for_title(&title), None, layout);
+        return Ok(SaveTarget {
+            path: abs_path_from_id(folder_path, &new_id, layout)?,
+            id: new_id,
+            previous: None,
+        });
+    };
+
+    let old_file_path = abs_path_from_id(folder_path, &existing_id, layout)?;
+
+    // Preserve directory prefix for notes in subfolders
+    let (dir_prefix, current_leaf) = match existing_id.rfind('/') {
+        Some(pos) => (Some(&existing_id[..pos]), &existing_id[pos + 1..]),
+        None => (None, existing_id.as_str()),
+    };
+    // Notes named under the old title rules (which kept markers like `##` and
+    // `**`) keep their name until their title actually changes
+    let named_by_legacy_title =
+        strategy.rename_target(current_leaf, &preview::legacy_title(content)).as_deref() == Some(current_leaf);
+    let desired_leaf = strategy
+        .rename_target(current_leaf, &title)
+        .filter(|leaf| leaf != current_leaf && !named_by_legacy_title);
+
+    match desired_leaf {
+        Some(leaf) => {
+            // Renamed notes keep their extension (.markdown stays .markdown)
+            let extension = old_file_path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or(naming::DEFAULT_EXTENSION)
+                .to_string();
+            let new_id = unique_note_id(folder_path, dir_prefix, &leaf, Some(&existing_id), layout);
+            let new_file_path = abs_path_with_extension(folder_path, &new_id, &extension)?;
+            let previous = (new_id != existing_id).then(|| (existing_id.clone(), old_file_path));
+            Ok(SaveTarget {
+                id: new_id,
+                path: new_file_path,
+                previous,
+            })
+        }
+        None => Ok(SaveTarget {
+            id: existing_id,
+            path: old_file_path,
+            previous: None,
+        }),
+    }
+}
+
+// Rewrite links in other notes that point at `old_id` so they point at `new_id`.
+// Every touched note is written atomically and re-indexed. A note that can't be
+// read or written is skipped and the rest are still updated; returns a message
+// for each skipped note.
+fn rewrite_inbound_links(fs: &FolderState, layout: &NoteLayout, old_id: &str, new_id: &str) -> Vec<String> {
+    let notes_root = PathBuf::from(&fs.folder);
+    let mut failures = Vec::new();
+
+    // Compute all rewrites against the current graph first, then write
+    let mut updates: Vec<(String, PathBuf, String)> = Vec::new();
+    {
+        let link_graph = fs.link_graph.read().expect("link graph read lock");
+        let resolver = link_graph.resolver(layout);
+        for affected in link_graph.inbound(&notes_root, layout, old_id) {
+            let content = abs_path_from_id(&notes_root, &affected.id, layout).and_then(|file_path| {
+                let content = encoding::read_to_string(&file_path).map_err(|e| e.to_string())?;
+                Ok((file_path, content))
+            });
+            let (file_path, content) = match content {
+                Ok(found) => found,
+                Err(e) => {
+                    failures.push(format!("Failed to read {}: {}", affected.id, e));
+                    continue;
+                }
+            };
+            if let Some((updated, _)) =
+                links::retarget_links(&content, &notes_root, &affected.id, &resolver, old_id, new_id)
+            {
+                updates.push((affected.id, file_path, updated));
+            }
+        }
+    }
+
+    for (id, file_path, updated) in updates {
+        if let Err(e) = write_note_atomic(&file_path, &updated) {
+            failures.push(format!("Failed to write {}: {}", id, e));
+            continue;
+        }
+        let modified = file_modified_secs(&file_path);
+        index_note_content(fs, layout, &id, &extract_title(&updated), &updated, modified);
+    }
+
+    failures
+}
+
+// Point the links a renamed note has to itself (e.g. `[[Old#Section]]`) at its new ID
+fn retarget_self_links(
+    fs: &FolderState,
+    layout: &NoteLayout,
+    content: &str,
+    old_id: &str,
+    new_id: &str,
+) -> Option<String> {
+    let link_graph = fs.link_graph.read().expect("link graph read lock");
+    let resolver = link_graph.resolver(layout);
+    links::retarget_links(content, Path::new(&fs.folder), old_id, &resolver, old_id, new_id)
+        .map(|(updated, _)| updated)
+}
+
+#[tauri::command]
+fn preview_rename_links(
+    folder: String,
+    id: String,
+    content: String,
+    state: State<AppState>,
+) -> Result<links::RenamePreview, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+
+    let strategy = folder_settings(&state, &folder).filename_strategy.unwrap_or_default();
+    let layout = note_layout(&state);
+    let target = resolve_save_target(&folder_path, Some(id.clone()), &content, strategy, &layout)?;
+    let affected = if target.previous.is_some() {
+        let link_graph = fs.link_graph.read().expect("link graph read lock");
+        link_graph.inbound(&folder_path, &layout, &id)
+    } else {
+        Vec::new()
+    };
+
+    Ok(links::RenamePreview {
+        old_id: id,
+        new_id: target.id,
+        affected,
     })
 }
 
@@ -883,67 +1299,80 @@ async fn save_note(
     folder: String,
     id: Option<String>,
     content: String,
+    rewrite_links: Option<bool>,
+    convert_to_utf8: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<Note, String> {
     let fs = get_folder_state(&state, &folder)?;
     let folder_path = PathBuf::from(&folder);
+    let settings = folder_settings(&state, &folder);
+
+    let (format_on_save, format_options) = {
+        let app_settings = state.settings.read().expect("settings read lock");
+        (
+            app_settings.format_on_save.unwrap_or(false),
+            app_settings.format_options.clone().unwrap_or_default(),
+        )
+    };
+    let content = if format_on_save {
+        format::format_markdown(&content, &format_options).unwrap_or_else(|e| {
+            eprintln!("Not formatting on save: {}", e);
+            content
+        })
+    } else {
+        content
+    };
 
     let title = extract_title(&content);
-    let sanitized_leaf = sanitize_filename(&title);
+    let layout = note_layout(&state);
 
     // Determine the file ID and path, handling renames
-    let (final_id, file_path, old_id) = if let Some(existing_id) = id {
-        // Preserve directory prefix for notes in subfolders
-        let (dir_prefix, desired_id) = if let Some(pos) = existing_id.rfind('/') {
-            let prefix = &existing_id[..pos];
-            (Some(prefix.to_string()), format!("{}/{}", prefix, sanitized_leaf))
-        } else {
-            (None, sanitized_leaf.clone())
-        };
-
-        let old_file_path = abs_path_from_id(&folder_path, &existing_id)?;
-
-        if existing_id != desired_id {
-            let mut new_id = desired_id.clone();
-            let mut counter = 1;
-
-            while new_id != existing_id
-                && abs_path_from_id(&folder_path, &new_id)
-                    .map(|p| p.exists())
-                    .unwrap_or(false)
-            {
-                new_id = if let Some(ref prefix) = dir_prefix {
-                    format!("{}/{}-{}", prefix, sanitized_leaf, counter)
-                } else {
-                    format!("{}-{}", sanitized_leaf, counter)
-                };
-                counter += 1;
-            }
-
-            let new_file_path = abs_path_from_id(&folder_path, &new_id)?;
-            (new_id, new_file_path, Some((existing_id, old_file_path)))
-        } else {
-            (existing_id, old_file_path, None)
-        }
-    } else {
-        // New notes go in root
-        let mut new_id = sanitized_leaf.clone();
-        let mut counter = 1;
+    let SaveTarget {
+        id: final_id,
+        path: file_path,
+        previous: old_id,
+    } = resolve_save_target(
+        &folder_path,
+        id,
+        &content,
+        settings.filename_strategy.unwrap_or_default(),
+        &layout,
+    )?;
 
-        while abs_path_from_id(&folder_path, &new_id)
-            .map(|p| p.exists())
-            .unwrap_or(false)
-        {
-            new_id = format!("{}-{}", sanitized_leaf, counter);
-            counter += 1;
+    let rewrite_links = rewrite_links.unwrap_or(true);
+    let content = match old_id {
+        Some((ref previous_id, _)) if rewrite_links => {
+            retarget_self_links(&fs, &layout, &content, previous_id, &final_id).unwrap_or(content)
         }
-
-        let new_file_path = abs_path_from_id(&folder_path, &new_id)?;
-        (new_id, new_file_path, None)
+        _ => content,
     };
 
+    // Keep the encoding and line endings of the file being replaced (or renamed),
+    // unless converting to UTF-8 or the folder forces a line ending
+    let encoding_source = old_id.as_ref().map(|(_, path)| path.clone()).unwrap_or_else(|| file_path.clone());
+    let text = content.clone();
+    let to_utf8 = convert_to_utf8.unwrap_or(false);
+    let forced_ending = settings.line_ending;
+    let (bytes, written_encoding, line_ending) = tokio::task::spawn_blocking(move || {
+        let ending = forced_ending.or_else(|| {
+            encoding::read_to_string(&encoding_source)
+                .ok()
+                .and_then(|existing| line_endings::detect(&existing))
+        });
+        let text = match ending {
+            Some(ending) => line_endings::apply(&text, ending),
+            None => text,
+        };
+        let (bytes, written_encoding) = encoding::encode_for_file(&encoding_source, &text, to_utf8)?;
+        let line_ending = line_endings::detect(&text).unwrap_or_default();
+        Ok::<_, std::io::Error>((bytes, written_encoding, line_ending))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
     // Write the file to the new path
-    fs::write(&file_path, &content)
+    fs::write(&file_path, &bytes)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -964,17 +1393,28 @@ async fn save_note(
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
 
-    // Update search index (delete old entry if renamed, then add new)
-    {
-        let index = fs.search_index.lock().expect("search index mutex");
-        if let Some(ref search_index) = *index {
-            if let Some((ref old_id_str, _)) = old_id {
-                let _ = search_index.delete_note(old_id_str);
-            }
-            let _ = search_index.index_note(&final_id, &title, &content, modified);
+    // Point links in other notes at the new ID (opt out with rewrite_links = false).
+    // Runs before the index update so links still resolve to the old ID.
+    let mut link_rewrite_errors = Vec::new();
+    if let Some((ref old_id_str, _)) = old_id {
+        if rewrite_links {
+            let fs_clone = Arc::clone(&fs);
+            let layout = layout.clone();
+            let (old, new) = (old_id_str.clone(), final_id.clone());
+            link_rewrite_errors = tokio::task::spawn_blocking(move || {
+                rewrite_inbound_links(&fs_clone, &layout, &old, &new)
+            })
+            .await
+            .map_err(|e| e.to_string())?;
         }
     }
 
+    // Update indexes (delete old entry if renamed, then add new)
+    if let Some((ref old_id_str, _)) = old_id {
+        unindex_note(&fs, old_id_str);
+    }
+    index_note_content(&fs, &layout, &final_id, &title, &content, modified);
+
     // Update cache (remove old entry if renamed)
     if let Some((ref old_id_str, _)) = old_id {
         let mut cache = fs.notes_cache.write().expect("cache write lock");
@@ -987,6 +1427,11 @@ async fn save_note(
         content,
         path: file_path.to_string_lossy().into_owned(),
         modified,
+        encoding: written_encoding.name().to_string(),
+        bom: written_encoding.bom,
+        line_ending,
+        link_rewrite_errors,
+        read_only: false,
     })
 }
 
@@ -995,20 +1440,15 @@ async fn delete_note(folder: String, id: String, state: State<'_, AppState>) ->
     let fs = get_folder_state(&state, &folder)?;
 
     let folder_path = PathBuf::from(&folder);
-    let file_path = abs_path_from_id(&folder_path, &id)?;
+    let file_path = abs_path_from_id(&folder_path, &id, &note_layout(&state))?;
     if file_path.exists() {
         fs::remove_file(&file_path)
             .await
             .map_err(|e| e.to_string())?;
     }
 
-    // Update search index
-    {
-        let index = fs.search_index.lock().expect("search index mutex");
-        if let Some(ref search_index) = *index {
-            let _ = search_index.delete_note(&id);
-        }
-    }
+    // Update indexes
+    unindex_note(&fs, &id);
 
     // Remove from cache
     {
@@ -1024,22 +1464,18 @@ async fn create_note(folder: String, state: State<'_, AppState>) -> Result<Note,
     let fs = get_folder_state(&state, &folder)?;
     let folder_path = PathBuf::from(&folder);
 
-    // Find unique filename starting from "Untitled"
-    let base_id = "Untitled".to_string();
-    let mut final_id = base_id.clone();
-    let mut counter = 1;
+    // Find unique filename starting from "Untitled" (or a timestamp ID)
+    let strategy = folder_settings(&state, &folder).filename_strategy.unwrap_or_default();
+    let layout = note_layout(&state);
+    let final_id = unique_note_id(&folder_path, None, &strategy.placeholder_name(), None, &layout);
 
-    while abs_path_from_id(&folder_path, &final_id)
-        .map(|p| p.exists())
-        .unwrap_or(false)
-    {
-        final_id = format!("{}-{}", base_id, counter);
-        counter += 1;
-    }
-
-    let display_title = extract_title_from_id(&final_id);
-    let content = format!("# {}\n\n", display_title);
-    let file_path = abs_path_from_id(&folder_path, &final_id)?;
+    let display_title = if strategy == naming::FilenameStrategy::Timestamp {
+        naming::PLACEHOLDER_NAME.to_string()
+    } else {
+        extract_title_from_id(&final_id)
+    };
+    let content = with_folder_line_ending(&state, &folder, format!("# {}\n\n", display_title));
+    let file_path = abs_path_from_id(&folder_path, &final_id, &layout)?;
 
     fs::write(&file_path, &content)
         .await
@@ -1050,13 +1486,9 @@ async fn create_note(folder: String, state: State<'_, AppState>) -> Result<Note,
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
 
-    // Update search index
-    {
-        let index = fs.search_index.lock().expect("search index mutex");
-        if let Some(ref search_index) = *index {
-            let _ = search_index.index_note(&final_id, &display_title, &content, modified);
-        }
-    }
+    // Update indexes
+    index_note_content(&fs, &layout, &final_id, &display_title, &content, modified);
+    let line_ending = line_endings::detect(&content).unwrap_or_default();
 
     Ok(Note {
         id: final_id,
@@ -1064,9 +1496,217 @@ async fn create_note(folder: String, state: State<'_, AppState>) -> Result<Note,
         content,
         path: file_path.to_string_lossy().into_owned(),
         modified,
+        encoding: encoding::TextEncoding::UTF8.name().to_string(),
+        bom: false,
+        line_ending,
+        link_rewrite_errors: Vec::new(),
+        read_only: false,
+    })
+}
+
+#[tauri::command]
+async fn list_templates(folder: String, state: State<'_, AppState>) -> Result<Vec<templates::TemplateInfo>, String> {
+    let _fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || templates::list_templates(&folder_path, &layout))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_note_from_template(
+    folder: String,
+    template_id: String,
+    vars: Option<HashMap<String, String>>,
+    state: State<'_, AppState>,
+) -> Result<Note, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+
+    let layout = note_layout(&state);
+    let template_path = abs_path_from_id(&folder_path.join(templates::TEMPLATES_DIR), &template_id, &layout)?;
+    let (template, _, _) = read_note_file(&template_path)
+        .await
+        .map_err(|e| format!("Failed to read template: {}", e))?;
+
+    let mut vars = vars.unwrap_or_default();
+    vars.entry("title".to_string())
+        .and_modify(|title| *title = title.trim().to_string())
+        .or_insert_with(|| "Untitled".to_string());
+    let expanded = templates::expand_template(&template, &vars, &chrono::Local::now());
+
+    // The template's file name wins over the folder's filename strategy
+    let leaf = match expanded.file_name {
+        Some(file_name) => sanitize_filename(&file_name),
+        None => folder_settings(&state, &folder)
+            .filename_strategy
+            .unwrap_or_default()
+            .name_for_title(&vars["title"]),
+    };
+    let final_id = unique_note_id(&folder_path, None, &leaf, None, &layout);
+
+    let content = with_folder_line_ending(&state, &folder, expanded.content);
+    let file_path = abs_path_from_id(&folder_path, &final_id, &layout)?;
+
+    fs::write(&file_path, &content)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let modified = file_modified_secs(&file_path);
+    let title = extract_title(&content);
+    let line_ending = line_endings::detect(&content).unwrap_or_default();
+
+    // Update indexes
+    index_note_content(&fs, &layout, &final_id, &title, &content, modified);
+
+    Ok(Note {
+        id: final_id,
+        title,
+        content,
+        path: file_path.to_string_lossy().into_owned(),
+        modified,
+        encoding: encoding::TextEncoding::UTF8.name().to_string(),
+        bom: false,
+        line_ending,
+        link_rewrite_errors: Vec::new(),
+        read_only: false,
     })
 }
 
+// Date range for list_daily_notes (YYYY-MM-DD, both ends optional and inclusive)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DateRange {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+fn daily_note_settings(state: &AppState) -> daily::DailyNoteSettings {
+    state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .daily_notes
+        .clone()
+        .unwrap_or_default()
+}
+
+// Open the daily note for a date (today when omitted), creating it if needed
+#[tauri::command]
+async fn open_daily_note(folder: String, date: Option<String>, state: State<'_, AppState>) -> Result<Note, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+    let settings = daily_note_settings(&state);
+
+    let now = chrono::Local::now();
+    let day = match date {
+        Some(date) => daily::parse_date(&date)?,
+        None => now.date_naive(),
+    };
+    let id = settings.note_id(day)?;
+    let layout = note_layout(&state);
+    let file_path = abs_path_from_id(&folder_path, &id, &layout)?;
+
+    if !file_path.exists() {
+        // Expand the template as if it were that day, at the current time
+        let when = day
+            .and_time(now.time())
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .unwrap_or(now);
+        let content = match settings.template.as_deref().filter(|t| !t.is_empty()) {
+            Some(template_id) => {
+                let templates_root = folder_path.join(templates::TEMPLATES_DIR);
+                let template_path = abs_path_from_id(&templates_root, template_id, &layout)?;
+                let (template, _, _) = read_note_file(&template_path)
+                    .await
+                    .map_err(|e| format!("Failed to read template: {}", e))?;
+                let vars = HashMap::from([("title".to_string(), id.clone())]);
+                templates::expand_template(&template, &vars, &when).content
+            }
+            None => format!("# {}\n\n", id),
+        };
+        let content = with_folder_line_ending(&state, &folder, content);
+
+        fs::write(&file_path, &content)
+            .await
+            .map_err(|e| e.to_string())?;
+        index_note_content(&fs, &layout, &id, &extract_title(&content), &content, file_modified_secs(&file_path));
+    }
+
+    let (content, detected, malformed) = read_note_file(&file_path).await?;
+    let line_ending = line_endings::detect(&content).unwrap_or_default();
+
+    Ok(Note {
+        title: extract_title(&content),
+        content,
+        path: file_path.to_string_lossy().into_owned(),
+        modified: file_modified_secs(&file_path),
+        id,
+        encoding: detected.name().to_string(),
+        bom: detected.bom,
+        line_ending,
+        link_rewrite_errors: Vec::new(),
+        read_only: malformed,
+    })
+}
+
+#[tauri::command]
+async fn list_daily_notes(
+    folder: String,
+    range: Option<DateRange>,
+    state: State<'_, AppState>,
+) -> Result<Vec<daily::DailyNote>, String> {
+    let _fs = get_folder_state(&state, &folder)?;
+    let settings = daily_note_settings(&state);
+    let range = range.unwrap_or_default();
+    let start = range.start.as_deref().map(daily::parse_date).transpose()?;
+    let end = range.end.as_deref().map(daily::parse_date).transpose()?;
+    let folder_path = PathBuf::from(&folder);
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || settings.list(&folder_path, &layout, start, end))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Closest existing daily note before a date (YYYY-MM-DD), skipping missing days
+#[tauri::command]
+async fn previous_daily_note(
+    folder: String,
+    date: String,
+    state: State<'_, AppState>,
+) -> Result<Option<daily::DailyNote>, String> {
+    let _fs = get_folder_state(&state, &folder)?;
+    let settings = daily_note_settings(&state);
+    let day = daily::parse_date(&date)?;
+    let folder_path = PathBuf::from(&folder);
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || settings.adjacent(&folder_path, &layout, day, false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Closest existing daily note after a date (YYYY-MM-DD), skipping missing days
+#[tauri::command]
+async fn next_daily_note(
+    folder: String,
+    date: String,
+    state: State<'_, AppState>,
+) -> Result<Option<daily::DailyNote>, String> {
+    let _fs = get_folder_state(&state, &folder)?;
+    let settings = daily_note_settings(&state);
+    let day = daily::parse_date(&date)?;
+    let folder_path = PathBuf::from(&folder);
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || settings.adjacent(&folder_path, &layout, day, true))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn get_settings(folder: String, state: State<AppState>) -> Result<Settings, String> {
     // Validate folder is initialized
@@ -1084,6 +1724,15 @@ fn update_settings(
     // Validate folder is initialized
     let _fs = get_folder_state(&state, &folder)?;
 
+    let attachment_settings = new_settings.attachments.clone().unwrap_or_default();
+    attachment_settings.validate()?;
+    if let Some(ref format_options) = new_settings.format_options {
+        format_options.validate()?;
+    }
+    if let Some(ref daily_notes) = new_settings.daily_notes {
+        daily_notes.validate()?;
+    }
+
     {
         let mut settings = state.settings.write().expect("settings write lock");
         *settings = new_settings;
@@ -1111,6 +1760,7 @@ async fn search_notes(folder: String, query: String, state: State<'_, AppState>)
     }
 
     // Check if search index is available and use it (scoped to drop lock before await)
+    let layout = note_layout(&state);
     let indexed_result = {
         let index = fs.search_index.lock().expect("search index mutex");
         (*index).as_ref().map(|search_index| {
@@ -1122,21 +1772,26 @@ async fn search_notes(folder: String, query: String, state: State<'_, AppState>)
         Some(Ok(results)) if !results.is_empty() => Ok(results),
         Some(Ok(_)) => {
             // Tantivy can miss partial/fuzzy matches; fall back to substring search.
-            fallback_search(&trimmed_query, &folder, &fs).await
+            fallback_search(&trimmed_query, &folder, &fs, &layout).await
         }
         Some(Err(e)) => {
             eprintln!("Tantivy search error, falling back to substring search: {}", e);
-            fallback_search(&trimmed_query, &folder, &fs).await
+            fallback_search(&trimmed_query, &folder, &fs, &layout).await
         }
         None => {
             // Fallback to simple search if index not available
-            fallback_search(&trimmed_query, &folder, &fs).await
+            fallback_search(&trimmed_query, &folder, &fs, &layout).await
         }
     }
 }
 
 // Fallback search when Tantivy index isn't available - searches title and full content
-async fn fallback_search(query: &str, folder: &str, fs: &Arc<FolderState>) -> Result<Vec<SearchResult>, String> {
+async fn fallback_search(
+    query: &str,
+    folder: &str,
+    fs: &Arc<FolderState>,
+    layout: &NoteLayout,
+) -> Result<Vec<SearchResult>, String> {
     // Collect cache data upfront to avoid holding lock during async operations
     let cache_data: Vec<(String, String, String, i64)> = {
         let cache = fs.notes_cache.read().expect("cache read lock");
@@ -1166,11 +1821,11 @@ async fn fallback_search(query: &str, folder: &str, fs: &Arc<FolderState>) -> Re
         }
 
         // Read file content asynchronously and search in it
-        let file_path = match abs_path_from_id(&folder_path, &id) {
+        let file_path = match abs_path_from_id(&folder_path, &id, layout) {
             Ok(p) => p,
             Err(_) => continue,
         };
-        if let Ok(content) = tokio::fs::read_to_string(&file_path).await {
+        if let Ok(content) = tokio::fs::read(&file_path).await.map(|bytes| encoding::decode(&bytes).0) {
             let content_lower = content.to_lowercase();
             if content_lower.contains(&query_lower) {
                 // Higher score if in title, lower if only in content
@@ -1220,8 +1875,12 @@ fn setup_file_watcher(
     let watcher = RecommendedWatcher::new(
         move |res: Result<notify::Event, notify::Error>| {
             if let Ok(event) = res {
+                let Some(state) = app_handle.try_state::<AppState>() else {
+                    return;
+                };
+                let layout = note_layout(&state);
                 for path in event.paths.iter() {
-                    let note_id = match id_from_abs_path(&notes_root, path) {
+                    let note_id = match id_from_abs_path(&notes_root, path, &layout) {
                         Some(id) => id,
                         None => continue,
                     };
@@ -1252,42 +1911,32 @@ fn setup_file_watcher(
                         _ => continue,
                     };
 
-                    // Update search index for external file changes
-                    if let Some(state) = app_handle.try_state::<AppState>() {
-                        let fs = {
-                            let states = state.folder_states.read().expect("folder_states read lock");
-                            states.get(&folder_key).cloned()
-                        };
-                        if let Some(fs) = fs {
-                            let index = fs.search_index.lock().expect("search index mutex");
-                            if let Some(ref search_index) = *index {
-                                match kind {
-                                    "created" | "modified" => {
-                                        match std::fs::read_to_string(path) {
-                                            Ok(content) => {
-                                                let title = extract_title(&content);
-                                                let modified = std::fs::metadata(path)
-                                                    .ok()
-                                                    .and_then(|m| m.modified().ok())
-                                                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                                                    .map(|d| d.as_secs() as i64)
-                                                    .unwrap_or(0);
-                                                let _ = search_index.index_note(&note_id, &title, &content, modified);
-                                            }
-                                            Err(_) => {
-                                                // File gone between event and read — treat as deletion
-                                                if !path.exists() {
-                                                    let _ = search_index.delete_note(&note_id);
-                                                }
-                                            }
-                                        }
+                    // Update indexes for external file changes
+                    let fs = {
+                        let states = state.folder_states.read().expect("folder_states read lock");
+                        states.get(&folder_key).cloned()
+                    };
+                    if let Some(fs) = fs {
+                        match kind {
+                            "created" | "modified" => {
+                                match encoding::read_to_string(path) {
+                                    Ok(content) => {
+                                        let title = extract_title(&content);
+                                        let modified = file_modified_secs(path);
+                                        index_note_content(&fs, &layout, &note_id, &title, &content, modified);
                                     }
-                                    "deleted" => {
-                                        let _ = search_index.delete_note(&note_id);
+                                    Err(_) => {
+                                        // File gone between event and read — treat as deletion
+                                        if !path.exists() {
+                                            unindex_note(&fs, &note_id);
+                                        }
                                     }
-                                    _ => {}
                                 }
                             }
+                            "deleted" => {
+                                unindex_note(&fs, &note_id);
+                            }
+                            _ => {}
                         }
                     }
 
@@ -1348,10 +1997,58 @@ fn copy_to_clipboard(app: AppHandle, text: String) -> Result<(), String> {
     app.clipboard().write_text(text).map_err(|e| e.to_string())
 }
 
+/// Create the attachment folder for a file imported into `note_id` and pick a
+/// free file name there, following the attachment settings.
+/// Returns (absolute path, path relative to the notes root).
+async fn prepare_attachment_path(
+    state: &AppState,
+    fs: &FolderState,
+    note_id: Option<&str>,
+    name: &str,
+    extension: &str,
+) -> Result<(PathBuf, String), String> {
+    let layout = note_layout(state);
+    let settings = layout.attachments().clone();
+    // A file with a note extension stored beside the notes would show up as a note
+    if settings.stores_beside_notes() && layout.is_note_extension(extension) {
+        return Err(format!(
+            "Can't attach .{} files next to notes, choose another attachment folder in settings",
+            extension
+        ));
+    }
+    let relative_dir = settings.target_dir(note_id)?;
+    let title = note_id.map(|id| {
+        fs.notes_cache
+            .read()
+            .expect("cache read lock")
+            .get(id)
+            .map(|note| note.title.clone())
+            .unwrap_or_else(|| extract_title_from_id(id))
+    });
+
+    let dir = PathBuf::from(&fs.folder).join(&relative_dir);
+    fs::create_dir_all(&dir).await.map_err(|e| e.to_string())?;
+
+    let context = attachments::NameContext {
+        title: title.as_deref(),
+        name,
+    };
+    let file_name = settings.unique_file_name(&dir, &context, extension);
+    let relative_path = if relative_dir.is_empty() {
+        file_name.clone()
+    } else {
+        format!("{}/{}", relative_dir, file_name)
+    };
+    Ok((dir.join(file_name), relative_path))
+}
+
+const UNSUPPORTED_IMAGE_ERROR: &str = "Not a supported image (PNG, JPEG, GIF, WebP, SVG or HEIC)";
+
 #[tauri::command]
 async fn save_clipboard_image(
     folder: String,
     base64_data: String,
+    note_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
     // Guard against empty clipboard payload
@@ -1360,7 +2057,7 @@ async fn save_clipboard_image(
     }
 
     // Validate folder is initialized
-    let _fs = get_folder_state(&state, &folder)?;
+    let fs = get_folder_state(&state, &folder)?;
 
     // Decode base64
     let image_data = base64::engine::general_purpose::STANDARD
@@ -1372,90 +2069,325 @@ async fn save_clipboard_image(
         return Err("Decoded image data is empty".to_string());
     }
 
-    // Create assets folder path
-    let assets_dir = PathBuf::from(&folder).join("assets");
-    fs::create_dir_all(&assets_dir)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    // Generate unique filename with timestamp
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-
-    let mut target_name = format!("screenshot-{}.png", timestamp);
-    let mut counter = 1;
-    let mut target_path = assets_dir.join(&target_name);
+    // Pick the extension from the actual content rather than assuming PNG
+    let format = images::sniff_format(&image_data).ok_or(UNSUPPORTED_IMAGE_ERROR)?;
+    let size_limit = state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .image_size_limit
+        .filter(|limit| *limit > 0);
 
-    while target_path.exists() {
-        target_name = format!("screenshot-{}-{}.png", timestamp, counter);
-        target_path = assets_dir.join(&target_name);
-        counter += 1;
+    // Shrink oversized raster images, then reuse an existing asset with
+    // identical content instead of writing a duplicate
+    let folder_path = PathBuf::from(&folder);
+    let layout = note_layout(&state);
+    let (image_data, format, existing) = tokio::task::spawn_blocking(move || {
+        let (data, format) = match size_limit {
+            Some(limit)
+                if image_data.len() as u64 > limit
+                    && !matches!(format, images::ImageFormat::Svg | images::ImageFormat::Heic) =>
+            {
+                images::fit_to_size(&image_data, limit as usize).unwrap_or((image_data, format))
+            }
+            _ => (image_data, format),
+        };
+        let existing = assets::find_identical(&folder_path, &layout, &data);
+        (data, format, existing)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    if let Some(existing_path) = existing {
+        return Ok(existing_path);
     }
+    let (target_path, relative_path) =
+        prepare_attachment_path(&state, &fs, note_id.as_deref(), "screenshot", format.extension()).await?;
 
     // Write the file
     fs::write(&target_path, &image_data)
         .await
         .map_err(|e| format!("Failed to write image: {}", e))?;
 
-    // Return relative path
-    Ok(format!("assets/{}", target_name))
+    Ok(relative_path)
 }
 
 #[tauri::command]
 async fn copy_image_to_assets(
     folder: String,
     source_path: String,
+    note_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
+    let imported = import_file(&state, &folder, &source_path, note_id.as_deref(), true).await?;
+    Ok(imported.path)
+}
+
+#[tauri::command]
+async fn import_attachment(
+    folder: String,
+    source_path: String,
+    note_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<attachments::ImportedAttachment, String> {
+    import_file(&state, &folder, &source_path, note_id.as_deref(), false).await
+}
+
+// Copy a file into the attachment folder, reusing an identical existing file.
+// With `images_only`, anything that isn't a supported image is rejected.
+async fn import_file(
+    state: &AppState,
+    folder: &str,
+    source_path: &str,
+    note_id: Option<&str>,
+    images_only: bool,
+) -> Result<attachments::ImportedAttachment, String> {
     // Validate folder is initialized
-    let _fs = get_folder_state(&state, &folder)?;
+    let fs = get_folder_state(state, folder)?;
 
-    let source = PathBuf::from(&source_path);
-    if !source.exists() {
-        return Err("Source image file does not exist".to_string());
+    let source = PathBuf::from(source_path);
+    if !source.is_file() {
+        return Err("Source file does not exist".to_string());
     }
 
-    // Get file extension
-    let extension = source
-        .extension()
-        .and_then(|e| e.to_str())
-        .ok_or("Invalid file extension")?;
+    // Enforce the optional size cap before reading anything
+    let size = fs::metadata(&source)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?
+        .len();
+    let size_limit = state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .attachment_size_limit
+        .filter(|limit| *limit > 0);
+    if let Some(limit) = size_limit {
+        if size > limit {
+            return Err(format!(
+                "File is too large ({}, limit is {})",
+                attachments::format_size(size),
+                attachments::format_size(limit)
+            ));
+        }
+    }
 
     // Get original filename (without extension)
     let original_name = source
         .file_stem()
         .and_then(|n| n.to_str())
-        .unwrap_or("image");
+        .unwrap_or("attachment");
 
     // Sanitize the filename
     let sanitized_name = sanitize_filename(original_name);
 
-    // Create assets folder path
-    let assets_dir = PathBuf::from(&folder).join("assets");
-    fs::create_dir_all(&assets_dir)
+    // Only the start of the file is needed to recognize images; the rest is
+    // streamed when hashing and copying, so large files aren't held in memory
+    let mut head = Vec::with_capacity(images::SNIFF_LEN);
+    fs::File::open(&source)
         .await
-        .map_err(|e| e.to_string())?;
-
-    // Generate unique filename
-    let mut target_name = format!("{}.{}", sanitized_name, extension);
-    let mut counter = 1;
-    let mut target_path = assets_dir.join(&target_name);
+        .map_err(|e| format!("Failed to read file: {}", e))?
+        .take(images::SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    // Trust image content over the extension, keeping the original
+    // spelling when it matches (e.g. .jpeg stays .jpeg)
+    let source_extension = source.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let extension = match images::sniff_format(&head) {
+        Some(format) if format.accepts_extension(source_extension) => source_extension.to_string(),
+        Some(format) => format.extension().to_string(),
+        None if images_only => return Err(UNSUPPORTED_IMAGE_ERROR.to_string()),
+        None => source_extension.to_string(),
+    };
+    let mime_type = attachments::mime_type_for_extension(&extension).to_string();
+    let inline = attachments::is_inline_renderable(&mime_type);
 
-    while target_path.exists() {
-        target_name = format!("{}-{}.{}", sanitized_name, counter, extension);
-        target_path = assets_dir.join(&target_name);
-        counter += 1;
+    // Reuse an existing asset with identical content instead of copying a duplicate
+    let folder_path = PathBuf::from(folder);
+    let layout = note_layout(state);
+    let source_clone = source.clone();
+    let existing =
+        tokio::task::spawn_blocking(move || assets::find_identical_file(&folder_path, &layout, &source_clone, size))
+            .await
+            .map_err(|e| e.to_string())?;
+    if let Some(path) = existing {
+        return Ok(attachments::ImportedAttachment {
+            path,
+            mime_type,
+            size,
+            inline,
+        });
     }
 
+    let (target_path, path) = prepare_attachment_path(state, &fs, note_id, &sanitized_name, &extension).await?;
+
     // Copy the file
     fs::copy(&source, &target_path)
         .await
-        .map_err(|e| format!("Failed to copy image: {}", e))?;
+        .map_err(|e| format!("Failed to copy file: {}", e))?;
+
+    Ok(attachments::ImportedAttachment {
+        path,
+        mime_type,
+        size,
+        inline,
+    })
+}
 
-    // Return both relative path and filename for frontend to construct the URL
-    Ok(format!("assets/{}", target_name))
+// Thumbnail cache of a notes folder, kept apart from other folders' so it can be pruned
+fn thumbnail_cache_dir(state: &AppState, folder: &str) -> PathBuf {
+    let key = assets::content_hash(folder.as_bytes());
+    state.app_data_dir.join("thumbnails").join(&key[..16])
+}
+
+// Get a downscaled copy of an image in the notes folder for display.
+// Returns an absolute path (the original file when no thumbnail is needed).
+#[tauri::command]
+async fn get_thumbnail(
+    folder: String,
+    asset_path: String,
+    max_px: u32,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let relative = links::normalize_relative_path("", &asset_path.replace('\\', "/"))
+        .ok_or("Invalid asset path")?;
+    let source = PathBuf::from(&folder).join(relative);
+    if !source.starts_with(&folder) {
+        return Err("Invalid asset path".to_string());
+    }
+    if !source.is_file() {
+        return Err("Image file does not exist".to_string());
+    }
+    let cache_dir = thumbnail_cache_dir(&state, &folder);
+    let image_hashes = fs.image_hashes.clone();
+
+    let thumbnail =
+        tokio::task::spawn_blocking(move || images::thumbnail(&source, &cache_dir, max_px, &image_hashes))
+            .await
+            .map_err(|e| e.to_string())??;
+    Ok(thumbnail.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+async fn find_unused_assets(folder: String, state: State<'_, AppState>) -> Result<Vec<assets::AssetFile>, String> {
+    let _fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || assets::find_unused(&folder_path, &layout))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn clean_unused_assets(
+    folder: String,
+    dry_run: bool,
+    paths: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<assets::AssetCleanupReport, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+    let layout = note_layout(&state);
+    let cache_dir = thumbnail_cache_dir(&state, &folder);
+    let image_hashes = fs.image_hashes.clone();
+
+    tokio::task::spawn_blocking(move || {
+        // Re-scan so files referenced since the preview are never moved;
+        // `paths` limits the cleanup to what the user confirmed.
+        let mut unused = assets::find_unused(&folder_path, &layout)?;
+        if let Some(ref selected) = paths {
+            unused.retain(|a| selected.contains(&a.path));
+        }
+        let total_size = unused.iter().map(|a| a.size).sum();
+
+        let trash_dir = if dry_run || unused.is_empty() {
+            None
+        } else {
+            Some(
+                assets::move_to_trash(&folder_path, &unused)
+                    .map_err(|e| format!("Failed to move assets to trash: {}", e))?,
+            )
+        };
+
+        // Drop thumbnails of images that are gone or have changed
+        if !dry_run {
+            let live: HashSet<String> = assets::list_assets(&folder_path, &layout)
+                .iter()
+                .filter(|asset| {
+                    let extension = asset.path.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+                    attachments::mime_type_for_extension(extension).starts_with("image/")
+                })
+                .filter_map(|asset| images::memo_hash(&image_hashes, &folder_path.join(&asset.path)).ok())
+                .collect();
+            if let Err(e) = images::prune_thumbnails(&cache_dir, &live) {
+                eprintln!("Failed to prune thumbnails: {}", e);
+            }
+        }
+
+        Ok(assets::AssetCleanupReport {
+            dry_run,
+            assets: unused,
+            total_size,
+            trash_dir,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn assets_report(
+    folder: String,
+    merge: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<assets::AssetsReport, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+    let merge = merge.unwrap_or(false);
+    let layout = note_layout(&state);
+
+    let layout_clone = layout.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let groups = assets::find_duplicate_groups(&folder_path, &layout_clone);
+        let wasted_size = groups
+            .iter()
+            .map(|g| g.size * g.duplicates.len() as u64)
+            .sum();
+        let outcome = if merge {
+            assets::merge_duplicates(&folder_path, &layout_clone, &groups)
+                .map_err(|e| format!("Failed to merge duplicate assets: {}", e))?
+        } else {
+            assets::MergeOutcome::default()
+        };
+        Ok::<_, String>(assets::AssetsReport {
+            groups,
+            wasted_size,
+            merged: merge,
+            rewritten_files: outcome.rewritten_files,
+            kept_duplicates: outcome.kept_duplicates,
+            errors: outcome.errors,
+            trash_dir: outcome.trash_dir,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    // Re-index notes whose references were rewritten
+    let folder_path = PathBuf::from(&folder);
+    for rel in &report.rewritten_files {
+        let file_path = folder_path.join(rel);
+        if let Some(id) = id_from_abs_path(&folder_path, &file_path, &layout) {
+            if let Ok(content) = encoding::read_to_string(&file_path) {
+                let modified = file_modified_secs(&file_path);
+                index_note_content(&fs, &layout, &id, &extract_title(&content), &content, modified);
+            }
+        }
+    }
+
+    Ok(report)
 }
 
 #[tauri::command]
@@ -1465,17 +2397,459 @@ fn rebuild_search_index(folder: String, app: AppHandle, state: State<AppState>)
     let index_path = get_search_index_path(&app, &folder).map_err(|e| e.to_string())?;
 
     // Create new index
+    let layout = note_layout(&state);
     let search_index = SearchIndex::new(&index_path).map_err(|e| e.to_string())?;
     search_index
-        .rebuild_index(&PathBuf::from(&folder))
+        .rebuild_index(&PathBuf::from(&folder), &layout)
         .map_err(|e| e.to_string())?;
 
-    let mut index = fs.search_index.lock().expect("search index mutex");
-    *index = Some(search_index);
+    {
+        let mut index = fs.search_index.lock().expect("search index mutex");
+        *index = Some(search_index);
+    }
+
+    rebuild_note_indexes(&fs, &PathBuf::from(&folder), &layout);
 
     Ok(())
 }
 
+#[tauri::command]
+fn list_tags(folder: String, state: State<AppState>) -> Result<Vec<tags::TagCount>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let tag_index = fs.tag_index.read().expect("tag index read lock");
+    Ok(tag_index.counts())
+}
+
+#[tauri::command]
+fn notes_with_tag(folder: String, tag: String, state: State<AppState>) -> Result<Vec<String>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let tag_index = fs.tag_index.read().expect("tag index read lock");
+    Ok(tag_index.notes_with(&tag))
+}
+
+#[tauri::command]
+fn list_tasks(
+    folder: String,
+    filter: Option<tasks::TaskFilter>,
+    state: State<AppState>,
+) -> Result<Vec<tasks::Task>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let task_index = fs.task_index.read().expect("task index read lock");
+    Ok(task_index.list(&filter.unwrap_or_default()))
+}
+
+// Check or uncheck the task on a line (1-based), leaving the rest of the file untouched
+#[tauri::command]
+async fn toggle_task(folder: String, id: String, line: usize, state: State<'_, AppState>) -> Result<tasks::Task, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let layout = note_layout(&state);
+    let file_path = abs_path_from_id(Path::new(&folder), &id, &layout)?;
+    let (content, _, _) = read_note_file(&file_path).await?;
+    let (updated, task) = tasks::toggle_task(&id, &content, line)?;
+
+    let write_path = file_path.clone();
+    let text = updated.clone();
+    tokio::task::spawn_blocking(move || write_note_atomic(&write_path, &text))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to write {}: {}", id, e))?;
+
+    let modified = file_modified_secs(&file_path);
+    index_note_content(&fs, &layout, &id, &extract_title(&updated), &updated, modified);
+    Ok(task)
+}
+
+// Heading tree of a note, served from the outline index (parsed and cached on a miss)
+#[tauri::command]
+async fn get_note_outline(
+    folder: String,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<outline::OutlineHeading>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    if let Some(outline) = fs.outline_index.read().expect("outline index read lock").get(&id) {
+        return Ok(outline);
+    }
+
+    let file_path = abs_path_from_id(Path::new(&folder), &id, &note_layout(&state))?;
+    let (content, _, _) = read_note_file(&file_path).await?;
+    let outline = outline::parse_outline(&content);
+    fs.outline_index
+        .write()
+        .expect("outline index write lock")
+        .insert(&id, outline.clone());
+    Ok(outline)
+}
+
+// Word count, reading time and element counts of a note (parsed and cached on a miss)
+#[tauri::command]
+async fn get_note_stats(folder: String, id: String, state: State<'_, AppState>) -> Result<stats::NoteStats, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    if let Some(note_stats) = fs.stats_index.read().expect("stats index read lock").get(&id) {
+        return Ok(note_stats);
+    }
+
+    let file_path = abs_path_from_id(Path::new(&folder), &id, &note_layout(&state))?;
+    let (content, _, _) = read_note_file(&file_path).await?;
+    let note_stats = stats::note_stats(&content);
+    fs.stats_index
+        .write()
+        .expect("stats index write lock")
+        .insert(&id, note_stats);
+    Ok(note_stats)
+}
+
+// Totals from the stats index plus sizes and per-day activity from the files
+#[tauri::command]
+async fn get_vault_stats(folder: String, state: State<'_, AppState>) -> Result<stats::VaultStats, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let unix_secs = |t: std::io::Result<std::time::SystemTime>| {
+            t.ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+        };
+        let files: Vec<stats::NoteFile> = list_note_files(Path::new(&fs.folder), &layout)
+            .into_iter()
+            .filter_map(|(id, file_path)| {
+                let metadata = std::fs::metadata(&file_path).ok()?;
+                Some(stats::NoteFile {
+                    id,
+                    size: metadata.len(),
+                    created: unix_secs(metadata.created()),
+                    modified: unix_secs(metadata.modified()).unwrap_or(0),
+                })
+            })
+            .collect();
+        let stats_index = fs.stats_index.read().expect("stats index read lock");
+        stats::vault_stats(&stats_index, &files)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_backlinks(folder: String, id: String, state: State<AppState>) -> Result<Vec<links::Backlink>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let link_graph = fs.link_graph.read().expect("link graph read lock");
+    Ok(link_graph.backlinks(Path::new(&folder), &note_layout(&state), &id))
+}
+
+#[tauri::command]
+fn get_outgoing_links(
+    folder: String,
+    id: String,
+    state: State<AppState>,
+) -> Result<Vec<links::OutgoingLink>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let link_graph = fs.link_graph.read().expect("link graph read lock");
+    Ok(link_graph.outgoing(Path::new(&folder), &note_layout(&state), &id))
+}
+
+#[tauri::command]
+fn get_unresolved_links(folder: String, state: State<AppState>) -> Result<Vec<links::UnresolvedLink>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let link_graph = fs.link_graph.read().expect("link graph read lock");
+    Ok(link_graph.unresolved(Path::new(&folder), &note_layout(&state)))
+}
+
+#[tauri::command]
+async fn check_vault_links(folder: String, state: State<'_, AppState>) -> Result<Vec<link_check::LinkIssue>, String> {
+    let _fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let notes: Vec<(String, String)> = list_note_files(&folder_path, &layout)
+            .into_iter()
+            .filter_map(|(id, file_path)| encoding::read_to_string(&file_path).ok().map(|c| (id, c)))
+            .collect();
+        link_check::check_links(&folder_path, &layout, &notes)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// Build the note graph from the folder's link graph and tag index
+fn build_note_graph(fs: &FolderState, layout: &NoteLayout) -> graph::NoteGraph {
+    let link_graph = fs.link_graph.read().expect("link graph read lock");
+    let tag_index = fs.tag_index.read().expect("tag index read lock");
+    graph::build(&link_graph, &tag_index, Path::new(&fs.folder), layout)
+}
+
+#[tauri::command]
+fn get_note_graph(folder: String, state: State<AppState>) -> Result<graph::NoteGraph, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    Ok(build_note_graph(&fs, &note_layout(&state)))
+}
+
+#[tauri::command]
+fn export_note_graph(folder: String, format: String, state: State<AppState>) -> Result<String, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let note_graph = build_note_graph(&fs, &note_layout(&state));
+    match format.to_ascii_lowercase().as_str() {
+        "graphml" => Ok(graph::to_graphml(&note_graph)),
+        "dot" | "gv" => Ok(graph::to_dot(&note_graph)),
+        other => Err(format!("Unsupported graph format: {}", other)),
+    }
+}
+
+#[tauri::command]
+async fn rename_tag(
+    folder: String,
+    old_tag: String,
+    new_tag: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let folder_path = PathBuf::from(&folder);
+
+    let old_normalized = tags::normalize_tag(&old_tag);
+    let new_trimmed = new_tag.trim().trim_start_matches('#').trim_end_matches('/').to_string();
+    if !tags::is_valid_tag(&old_normalized) {
+        return Err(format!("Invalid tag: {}", old_tag));
+    }
+    if !tags::is_valid_tag(&new_trimmed) {
+        return Err(format!("Invalid tag: {}", new_tag));
+    }
+
+    let affected = fs
+        .tag_index
+        .read()
+        .expect("tag index read lock")
+        .notes_with(&old_normalized);
+
+    let layout = note_layout(&state);
+    let mut changed = Vec::new();
+    for id in affected {
+        let file_path = abs_path_from_id(&folder_path, &id, &layout)?;
+        let content = match fs::read(&file_path).await {
+            Ok(bytes) => encoding::decode(&bytes).0,
+            Err(_) => continue,
+        };
+        let Some(updated) = tags::rename_tag_in_content(&content, &old_normalized, &new_trimmed) else {
+            continue;
+        };
+
+        let write_path = file_path.clone();
+        let text = updated.clone();
+        tokio::task::spawn_blocking(move || write_note_atomic(&write_path, &text))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to write {}: {}", id, e))?;
+
+        let modified = file_modified_secs(&file_path);
+        index_note_content(&fs, &layout, &id, &extract_title(&updated), &updated, modified);
+        changed.push(id);
+    }
+
+    Ok(changed)
+}
+
+// Convert every note to one line ending: the given one, else the folder's
+// setting, else LF. Returns the IDs of notes that changed.
+#[tauri::command]
+async fn normalize_line_endings(
+    folder: String,
+    line_ending: Option<line_endings::LineEnding>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let ending = line_ending
+        .or(folder_settings(&state, &folder).line_ending)
+        .unwrap_or_default();
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let mut changed = Vec::new();
+        for (id, file_path) in list_note_files(Path::new(&fs.folder), &layout) {
+            let Ok(content) = encoding::read_to_string(&file_path) else {
+                continue;
+            };
+            let updated = line_endings::apply(&content, ending);
+            if updated == content {
+                continue;
+            }
+            write_note_atomic(&file_path, &updated).map_err(|e| format!("Failed to write {}: {}", id, e))?;
+            let modified = file_modified_secs(&file_path);
+            index_note_content(&fs, &layout, &id, &extract_title(&updated), &updated, modified);
+            changed.push(id);
+        }
+        Ok(changed)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Add spaces between CJK and Latin text, leaving code, URLs and frontmatter alone
+#[tauri::command]
+fn format_cjk_spacing(text: String) -> String {
+    cjk::format_spacing(&text)
+}
+
+// Apply CJK-Latin spacing to a note on disk. Returns whether it changed.
+#[tauri::command]
+async fn format_note(folder: String, id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let layout = note_layout(&state);
+    let file_path = abs_path_from_id(Path::new(&folder), &id, &layout)?;
+    let (content, _, _) = read_note_file(&file_path).await?;
+    let updated = cjk::format_spacing(&content);
+    if updated == content {
+        return Ok(false);
+    }
+
+    let write_path = file_path.clone();
+    let text = updated.clone();
+    tokio::task::spawn_blocking(move || write_note_atomic(&write_path, &text))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to write {}: {}", id, e))?;
+
+    let modified = file_modified_secs(&file_path);
+    index_note_content(&fs, &layout, &id, &extract_title(&updated), &updated, modified);
+    Ok(true)
+}
+
+// CJK-Latin spacing for every note. Only reports what would change unless
+// dry_run is false.
+#[tauri::command]
+async fn format_vault(
+    folder: String,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<cjk::SpacingChange>, String> {
+    let fs = get_folder_state(&state, &folder)?;
+    let dry_run = dry_run.unwrap_or(true);
+    let layout = note_layout(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let mut changes = Vec::new();
+        for (id, file_path) in list_note_files(Path::new(&fs.folder), &layout) {
+            let Ok(content) = encoding::read_to_string(&file_path) else {
+                continue;
+            };
+            let insertions = cjk::spacing_insertions(&content);
+            let Some(change) = cjk::SpacingChange::new(&id, &content, &insertions) else {
+                continue;
+            };
+            if !dry_run {
+                let updated = cjk::insert_spaces(&content, &insertions);
+                write_note_atomic(&file_path, &updated).map_err(|e| format!("Failed to write {}: {}", id, e))?;
+                let modified = file_modified_secs(&file_path);
+                index_note_content(&fs, &layout, &id, &extract_title(&updated), &updated, modified);
+            }
+            changes.push(change);
+        }
+        Ok(changes)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Format markdown text. Options default to the ones in settings.
+#[tauri::command]
+fn format_markdown(
+    content: String,
+    options: Option<format::FormatOptions>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_else(|| {
+        state
+            .settings
+            .read()
+            .expect("settings read lock")
+            .format_options
+            .clone()
+            .unwrap_or_default()
+    });
+    format::format_markdown(&content, &options)
+}
+
+// Render a note to a standalone HTML file. Returns the file written.
+#[tauri::command]
+async fn export_note_html(
+    folder: String,
+    id: String,
+    options: export::HtmlExportOptions,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    get_folder_state(&state, &folder)?;
+    let notes_root = PathBuf::from(&folder);
+    let layout = note_layout(&state);
+    let file_path = abs_path_from_id(&notes_root, &id, &layout)?;
+    let (content, _, _) = read_note_file(&file_path).await?;
+    let style = {
+        let settings = state.settings.read().expect("settings read lock");
+        export::stylesheet(&settings.theme, options.theme)
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let title = extract_title(&content);
+        export::export_note(&notes_root, &layout, &id, &title, &content, &style, &options)?;
+        Ok(options.output_path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Publish the folder as a static site in out_dir: a page per note, an index,
+// tag pages and a search index
+#[tauri::command]
+async fn export_vault_site(
+    folder: String,
+    out_dir: String,
+    options: Option<export::SiteExportOptions>,
+    state: State<'_, AppState>,
+) -> Result<export::SiteExportReport, String> {
+    get_folder_state(&state, &folder)?;
+    let options = options.unwrap_or_default();
+    let layout = note_layout(&state);
+    let style = {
+        let settings = state.settings.read().expect("settings read lock");
+        export::stylesheet(&settings.theme, options.theme)
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let notes_root = PathBuf::from(&folder);
+        let out_dir = PathBuf::from(&out_dir);
+        std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create {}: {}", out_dir.display(), e))?;
+        // Copying assets into the notes folder would overwrite them with themselves
+        let inside_notes = match (out_dir.canonicalize(), notes_root.canonicalize()) {
+            (Ok(out), Ok(root)) => out.starts_with(root),
+            _ => false,
+        };
+        if inside_notes {
+            return Err("The site can't be exported into the notes folder".to_string());
+        }
+
+        let mut notes: Vec<export::SiteNote> = Vec::new();
+        let mut skipped: Vec<String> = Vec::new();
+        for (id, file_path) in list_note_files(&notes_root, &layout) {
+            match encoding::read_to_string(&file_path) {
+                Ok(content) => notes.push(export::SiteNote { id, content }),
+                Err(e) => {
+                    let relative = file_path.strip_prefix(&notes_root).unwrap_or(&file_path);
+                    skipped.push(format!("{}: {}", relative.display(), e));
+                }
+            }
+        }
+        let title = options.title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| {
+            notes_root
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "Notes".to_string())
+        });
+        let mut report = export::export_site(&notes_root, &layout, &out_dir, &notes, &title, &style)?;
+        report.skipped = skipped;
+        Ok(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 // UI helper commands - wrap Tauri plugins for consistent invoke-based API
 
 #[tauri::command]
@@ -1879,17 +3253,6 @@ async fn ai_execute_codex(file_path: String, prompt: String) -> Result<AiExecuti
     .await
 }
 
-/// Check if a file extension is a supported markdown extension.
-fn is_markdown_extension(path: &Path) -> bool {
-    path.extension()
-        .and_then(|e| e.to_str())
-        .map(|s| {
-            let lower = s.to_ascii_lowercase();
-            lower == "md" || lower == "markdown"
-        })
-        .unwrap_or(false)
-}
-
 /// Generate a stable window label from a folder path.
 fn window_label_for_folder(folder: &str) -> String {
     use std::collections::hash_map::DefaultHasher;
@@ -1983,19 +3346,20 @@ fn create_directory_window(app: &AppHandle, folder: &str, select_file: Option<&s
 
 /// Handle opening a markdown file: extract parent directory, derive note ID,
 /// create/focus directory window.
-fn handle_open_file(app: &AppHandle, path: &Path) {
+fn handle_open_file(app: &AppHandle, path: &Path, layout: &NoteLayout) {
     let parent = match path.parent() {
         Some(p) => p.to_string_lossy().into_owned(),
         None => return,
     };
 
-    let note_id = id_from_abs_path(Path::new(&parent), path);
+    let note_id = id_from_abs_path(Path::new(&parent), path, layout);
 
     let _ = create_directory_window(app, &parent, note_id.as_deref());
 }
 
 // Handle CLI arguments: open .md files as directory windows
 fn handle_cli_args(app: &AppHandle, args: &[String], cwd: &str) {
+    let layout = note_layout(&app.state::<AppState>());
     let mut opened_file = false;
 
     for arg in args.iter().skip(1) {
@@ -2010,9 +3374,9 @@ fn handle_cli_args(app: &AppHandle, args: &[String], cwd: &str) {
             PathBuf::from(cwd).join(arg)
         };
 
-        if is_markdown_extension(&path) && path.is_file() {
+        if layout.is_note_file(&path) && path.is_file() {
             opened_file = true;
-            handle_open_file(app, &path);
+            handle_open_file(app, &path, &layout);
         }
     }
 
@@ -2090,9 +3454,10 @@ pub fn run() {
             // Handle drag-and-drop of .md files onto any window
             if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
                 let app = window.app_handle();
+                let layout = note_layout(&app.state::<AppState>());
                 for path in paths {
-                    if is_markdown_extension(path) && path.is_file() {
-                        handle_open_file(app, path);
+                    if layout.is_note_file(path) && path.is_file() {
+                        handle_open_file(app, path, &layout);
                     }
                 }
             }
@@ -2103,17 +3468,50 @@ pub fn run() {
             list_notes,
             read_note,
             save_note,
+            preview_rename_links,
             delete_note,
             create_note,
+            list_templates,
+            create_note_from_template,
+            open_daily_note,
+            list_daily_notes,
+            previous_daily_note,
+            next_daily_note,
             get_settings,
             update_settings,
             write_file,
             search_notes,
             start_file_watcher,
             rebuild_search_index,
+            list_tags,
+            notes_with_tag,
+            rename_tag,
+            normalize_line_endings,
+            format_cjk_spacing,
+            format_markdown,
+            format_note,
+            format_vault,
+            export_note_html,
+            export_vault_site,
+            get_note_outline,
+            get_note_stats,
+            get_vault_stats,
+            list_tasks,
+            toggle_task,
+            get_backlinks,
+            get_outgoing_links,
+            get_unresolved_links,
+            get_note_graph,
+            export_note_graph,
+            check_vault_links,
             copy_to_clipboard,
             copy_image_to_assets,
+            import_attachment,
+            get_thumbnail,
             save_clipboard_image,
+            find_unused_assets,
+            clean_unused_assets,
+            assets_report,
             open_folder_dialog,
             open_in_file_manager,
             open_url_safe,
@@ -2130,10 +3528,11 @@ pub fn run() {
     app.run(|_app_handle, _event| {
         #[cfg(target_os = "macos")]
         if let tauri::RunEvent::Opened { urls } = _event {
+            let layout = note_layout(&_app_handle.state::<AppState>());
             for url in urls {
                 if let Ok(path) = url.to_file_path() {
-                    if is_markdown_extension(&path) && path.is_file() {
-                        handle_open_file(_app_handle, &path);
+                    if layout.is_note_file(&path) && path.is_file() {
+                        handle_open_file(_app_handle, &path, &layout);
                     }
                 }
             }
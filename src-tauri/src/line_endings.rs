@@ -0,0 +1,34 @@
+//! Line ending detection and conversion, so saves don't flip a file's endings.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+/// The dominant line ending in `text`, or None if it has no line breaks.
+/// Ties go to LF.
+pub fn detect(text: &str) -> Option<LineEnding> {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    if crlf + lf == 0 {
+        None
+    } else if crlf > lf {
+        Some(LineEnding::Crlf)
+    } else {
+        Some(LineEnding::Lf)
+    }
+}
+
+/// `text` with every line break converted to `ending`. Lone `\r` is left alone.
+pub fn apply(text: &str, ending: LineEnding) -> String {
+    let lf = text.replace("\r\n", "\n");
+    match ending {
+        LineEnding::Lf => lf,
+        LineEnding::Crlf => lf.replace('\n', "\r\n"),
+    }
+}
@@ -0,0 +1,277 @@
+//! Folder-wide link checker: broken note links, missing files, missing anchors, bad URLs.
+
+use crate::assets;
+use crate::links::{self, Link, Resolution, Resolver};
+use crate::NoteLayout;
+use crate::markdown::{self, LineKind};
+use crate::outline::{self, OutlineHeading};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "ico", "avif", "heic", "heif", "tif", "tiff",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkIssueKind {
+    /// Note link that doesn't resolve to any note
+    BrokenLink,
+    /// Image (or other file) that doesn't exist
+    MissingAsset,
+    /// Heading or block anchor that doesn't exist in the target note
+    MissingAnchor,
+    /// External URL that fails to parse
+    InvalidUrl,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkIssue {
+    pub id: String,
+    pub kind: LinkIssueKind,
+    pub target: String,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Anchors a note can be linked to: heading texts, heading slugs and `^block` IDs.
+#[derive(Debug, Default)]
+struct Anchors {
+    headings: HashSet<String>,
+    blocks: HashSet<String>,
+}
+
+impl Anchors {
+    fn from_content(content: &str) -> Self {
+        let mut anchors = Anchors::default();
+        // The outline sees ATX and Setext headings and gives repeated slugs -1, -2, ... suffixes
+        let mut pending: Vec<OutlineHeading> = outline::parse_outline(content);
+        while let Some(heading) = pending.pop() {
+            anchors.headings.insert(heading.text.to_lowercase());
+            anchors.headings.insert(heading.slug);
+            pending.extend(heading.children);
+        }
+        for line in markdown::lines(content) {
+            if line.kind != LineKind::Text {
+                continue;
+            }
+            if let Some(block) = line.text.trim_end().rsplit(' ').next().and_then(|w| w.strip_prefix('^')) {
+                anchors.blocks.insert(block.to_lowercase());
+            }
+        }
+        anchors
+    }
+
+    fn contains(&self, anchor: &str) -> bool {
+        let anchor = anchor.trim();
+        if let Some(block) = anchor.strip_prefix('^') {
+            return self.blocks.contains(&block.to_lowercase());
+        }
+        let lower = anchor.to_lowercase();
+        self.headings.contains(&lower) || self.headings.contains(&markdown::heading_slug(anchor))
+    }
+}
+
+fn is_image_path(target: &str) -> bool {
+    Path::new(target)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Check every link in the given notes. `notes` holds (ID, content) pairs;
+/// `notes_root` is used to check that linked files exist.
+pub fn check_links(notes_root: &Path, layout: &NoteLayout, notes: &[(String, String)]) -> Vec<LinkIssue> {
+    let ids: BTreeSet<String> = notes.iter().map(|(id, _)| id.clone()).collect();
+    let resolver = Resolver::new(&ids, layout);
+    let anchors: HashMap<&str, Anchors> = notes
+        .iter()
+        .map(|(id, content)| (id.as_str(), Anchors::from_content(content)))
+        .collect();
+    let file_names = collect_file_names(notes_root);
+
+    let mut issues = Vec::new();
+    for (id, content) in notes {
+        for link in links::extract_links(content) {
+            if let Some((kind, message)) = check_link(notes_root, id, &link, &resolver, &anchors, &file_names) {
+                issues.push(LinkIssue {
+                    id: id.clone(),
+                    kind,
+                    target: link.target.clone(),
+                    message,
+                    line: link.line,
+                    column: link.column,
+                });
+            }
+        }
+    }
+
+    issues.sort_by(|a, b| {
+        a.id.cmp(&b.id)
+            .then(a.line.cmp(&b.line))
+            .then(a.column.cmp(&b.column))
+    });
+    issues
+}
+
+fn check_link(
+    notes_root: &Path,
+    source_id: &str,
+    link: &Link,
+    resolver: &Resolver<'_>,
+    anchors: &HashMap<&str, Anchors>,
+    file_names: &HashSet<String>,
+) -> Option<(LinkIssueKind, String)> {
+    let missing_anchor = |note_id: &str, anchor: &str| {
+        let found = anchors.get(note_id).is_some_and(|a| a.contains(anchor));
+        (!found).then(|| {
+            (
+                LinkIssueKind::MissingAnchor,
+                format!("Heading or block \"{}\" not found in {}", anchor, note_id),
+            )
+        })
+    };
+
+    let missing_file = |target: &str| {
+        let what = if is_image_path(target) { "Image" } else { "File" };
+        (LinkIssueKind::MissingAsset, format!("{} \"{}\" not found", what, target))
+    };
+
+    if !link.wiki_syntax && links::is_external_target(&link.target) {
+        // asset:// and file:// URLs (how the editor links pasted and dropped
+        // files) point into the folder, so the file must exist
+        if let Some(rel) = assets::resolve_local_target(notes_root, source_id, &link.target) {
+            return (!notes_root.join(rel).exists()).then(|| missing_file(&link.target));
+        }
+        return match url::Url::parse(&link.target) {
+            Ok(url) if url.cannot_be_a_base() || url.has_host() || url.scheme() == "file" => None,
+            Ok(_) => Some((LinkIssueKind::InvalidUrl, "URL has no host".to_string())),
+            Err(e) => Some((LinkIssueKind::InvalidUrl, format!("Invalid URL: {}", e))),
+        };
+    }
+
+    match resolver.resolve(notes_root, source_id, link) {
+        Resolution::Note(target) => match link.anchor.as_deref() {
+            Some(anchor) if !anchor.is_empty() => missing_anchor(&target, anchor),
+            _ => None,
+        },
+        Resolution::Unresolved => Some((
+            LinkIssueKind::BrokenLink,
+            format!("Note \"{}\" not found", link.target),
+        )),
+        Resolution::NotANote => {
+            if link.target.is_empty() {
+                // Same-note anchor like [see below](#details)
+                return match link.anchor.as_deref() {
+                    Some(anchor) if !anchor.is_empty() => missing_anchor(source_id, anchor),
+                    _ => None,
+                };
+            }
+            if file_exists(notes_root, source_id, link, file_names) {
+                return None;
+            }
+            Some(missing_file(&link.target))
+        }
+    }
+}
+
+/// Whether a non-note link target exists: relative to the source note (or the
+/// root for a leading `/`). Wiki embeds also match relative to the root and by
+/// file name anywhere in the folder; markdown links don't, like in a browser.
+fn file_exists(notes_root: &Path, source_id: &str, link: &Link, file_names: &HashSet<String>) -> bool {
+    let source_dir = source_id.rfind('/').map(|pos| &source_id[..pos]).unwrap_or("");
+    let root_relative = link.wiki_syntax.then(|| links::normalize_relative_path("", &link.target));
+    let candidates: Vec<PathBuf> = links::normalize_relative_path(source_dir, &link.target)
+        .into_iter()
+        .chain(root_relative.flatten())
+        .map(|rel| notes_root.join(rel))
+        .collect();
+    if candidates.iter().any(|p| p.exists()) {
+        return true;
+    }
+    if link.wiki_syntax {
+        let name = link.target.rsplit('/').next().unwrap_or(&link.target);
+        return file_names.contains(&name.to_lowercase());
+    }
+    false
+}
+
+/// Lowercase names of all files in the folder (dot-directories skipped).
+fn collect_file_names(notes_root: &Path) -> HashSet<String> {
+    walkdir::WalkDir::new(notes_root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.file_name().to_string_lossy().to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty notes folder for one test.
+    fn notes_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("verso-link-check-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("assets")).unwrap();
+        root
+    }
+
+    fn asset_url(path: &Path) -> String {
+        format!("asset://localhost/{}", urlencoding::encode(&path.to_string_lossy()))
+    }
+
+    #[test]
+    fn missing_pasted_image_is_reported() {
+        let root = notes_root("asset-url");
+        std::fs::write(root.join("assets/here.png"), b"png").unwrap();
+        let content = format!(
+            "# Note\n\n![ok]({})\n\n![gone]({})\n",
+            asset_url(&root.join("assets/here.png")),
+            asset_url(&root.join("assets/missing.png"))
+        );
+        let layout = NoteLayout::new(&crate::Settings::default());
+
+        let issues = check_links(&root, &layout, &[("note".to_string(), content)]);
+
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(issues[0].kind, LinkIssueKind::MissingAsset);
+        assert!(issues[0].target.ends_with("missing.png"));
+        assert_eq!(issues[0].line, 5);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn web_urls_are_not_checked_on_disk() {
+        let root = notes_root("web-url");
+        let content = "[site](https://example.com/missing.png)\n".to_string();
+        let layout = NoteLayout::new(&crate::Settings::default());
+
+        assert!(check_links(&root, &layout, &[("note".to_string(), content)]).is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn links_to_dotted_note_names_resolve() {
+        let root = notes_root("dotted");
+        let content = "[ok](meeting.2024-01-15)\n\n[gone](meeting.2024-01-16)\n".to_string();
+        let notes = [
+            ("index".to_string(), content),
+            ("meeting.2024-01-15".to_string(), "# Meeting\n".to_string()),
+        ];
+        let layout = NoteLayout::new(&crate::Settings::default());
+
+        let issues = check_links(&root, &layout, &notes);
+
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(issues[0].kind, LinkIssueKind::BrokenLink);
+        assert_eq!(issues[0].target, "meeting.2024-01-16");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
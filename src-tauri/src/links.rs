@@ -0,0 +1,603 @@
+//! Wiki-link / markdown link extraction and the per-folder link graph.
+
+use crate::markdown::{self, LineKind};
+use crate::NoteLayout;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::LazyLock;
+
+static WIKI_LINK_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(!?)\[\[([^\[\]\n]+?)\]\]").unwrap());
+static MARKDOWN_LINK_RE: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r#"(!?)\[((?:[^\[\]]|\[[^\[\]]*\])*)\]\(\s*(<[^<>\n]*>|[^()\s]+(?:\([^()\s]*\)[^()\s]*)*)(?:\s+(?:"[^"]*"|'[^']*'))?\s*\)"#).unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    Wiki,
+    Markdown,
+    Embed,
+}
+
+/// A link as written in a note.
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub kind: LinkKind,
+    /// Whether the link uses `[[wiki]]` syntax (embeds can use either syntax)
+    pub wiki_syntax: bool,
+    /// Target as written, without anchor or alias (percent-decoded for markdown links)
+    pub target: String,
+    /// Heading or block anchor after `#`, if any
+    pub anchor: Option<String>,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, counted in characters
+    pub column: usize,
+    /// Byte range of the whole link in the note content
+    pub span: (usize, usize),
+    /// Byte range of the raw target (anchor excluded) in the note content
+    pub target_span: (usize, usize),
+    /// Trimmed text of the line containing the link
+    pub context: String,
+}
+
+/// Where a link points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// An existing note
+    Note(String),
+    /// Looks like a note link, but no such note exists
+    Unresolved,
+    /// An external URL, asset, or same-note anchor
+    NotANote,
+}
+
+// Backlink to a note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backlink {
+    pub source: String,
+    pub kind: LinkKind,
+    pub line: usize,
+    pub column: usize,
+    pub context: String,
+}
+
+// Outgoing link from a note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutgoingLink {
+    pub target: String,
+    pub resolved_id: Option<String>,
+    pub anchor: Option<String>,
+    pub kind: LinkKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+// Note whose links would be rewritten by a rename
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedNote {
+    pub id: String,
+    pub link_count: usize,
+}
+
+// What renaming a note on save would do to links in other notes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamePreview {
+    pub old_id: String,
+    pub new_id: String,
+    pub affected: Vec<AffectedNote>,
+}
+
+// Link that doesn't resolve to any note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedLink {
+    pub source: String,
+    pub target: String,
+    pub kind: LinkKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Per-folder link graph. Links are stored as written and resolved against the
+/// current set of notes on query, so creating a note fixes links pointing at it.
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    links: HashMap<String, Vec<Link>>,
+    titles: HashMap<String, String>,
+    notes: BTreeSet<String>,
+}
+
+impl LinkGraph {
+    pub fn update(&mut self, id: &str, title: &str, content: &str) {
+        self.notes.insert(id.to_string());
+        self.titles.insert(id.to_string(), title.to_string());
+        self.links.insert(id.to_string(), extract_links(content));
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.notes.remove(id);
+        self.titles.remove(id);
+        self.links.remove(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.notes.clear();
+        self.titles.clear();
+        self.links.clear();
+    }
+
+    /// All note IDs in the graph, sorted.
+    pub fn notes(&self) -> impl Iterator<Item = &str> {
+        self.notes.iter().map(|id| id.as_str())
+    }
+
+    pub fn title_of(&self, id: &str) -> Option<&str> {
+        self.titles.get(id).map(|t| t.as_str())
+    }
+
+    /// Resolved note-to-note links as (source, target, kind), in source order.
+    pub fn resolved_edges(&self, notes_root: &Path, layout: &NoteLayout) -> Vec<(String, String, LinkKind)> {
+        let resolver = self.resolver(layout);
+        let mut edges = Vec::new();
+        for source in &self.notes {
+            for link in self.links.get(source).into_iter().flatten() {
+                if let Resolution::Note(target) = resolver.resolve(notes_root, source, link) {
+                    edges.push((source.clone(), target, link.kind));
+                }
+            }
+        }
+        edges
+    }
+
+    pub fn resolver<'a>(&'a self, layout: &'a NoteLayout) -> Resolver<'a> {
+        Resolver::new(&self.notes, layout)
+    }
+
+    /// Links whose target resolves to `id`, ordered by source note and position.
+    pub fn backlinks(&self, notes_root: &Path, layout: &NoteLayout, id: &str) -> Vec<Backlink> {
+        let resolver = self.resolver(layout);
+        let mut result = Vec::new();
+        for source in &self.notes {
+            for link in self.links.get(source).into_iter().flatten() {
+                if resolver.resolve(notes_root, source, link) == Resolution::Note(id.to_string()) {
+                    result.push(Backlink {
+                        source: source.clone(),
+                        kind: link.kind,
+                        line: link.line,
+                        column: link.column,
+                        context: link.context.clone(),
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    /// Note links written in `id`, with their resolved targets.
+    pub fn outgoing(&self, notes_root: &Path, layout: &NoteLayout, id: &str) -> Vec<OutgoingLink> {
+        let resolver = self.resolver(layout);
+        self.links
+            .get(id)
+            .into_iter()
+            .flatten()
+            .filter_map(|link| {
+                let resolved_id = match resolver.resolve(notes_root, id, link) {
+                    Resolution::Note(target) => Some(target),
+                    Resolution::Unresolved => None,
+                    Resolution::NotANote => return None,
+                };
+                Some(OutgoingLink {
+                    target: link.target.clone(),
+                    resolved_id,
+                    anchor: link.anchor.clone(),
+                    kind: link.kind,
+                    line: link.line,
+                    column: link.column,
+                })
+            })
+            .collect()
+    }
+
+    /// Other notes linking to `id`, with the number of links in each.
+    pub fn inbound(&self, notes_root: &Path, layout: &NoteLayout, id: &str) -> Vec<AffectedNote> {
+        let mut counts: Vec<AffectedNote> = Vec::new();
+        for backlink in self.backlinks(notes_root, layout, id) {
+            if backlink.source == id {
+                continue;
+            }
+            match counts.last_mut() {
+                Some(last) if last.id == backlink.source => last.link_count += 1,
+                _ => counts.push(AffectedNote {
+                    id: backlink.source,
+                    link_count: 1,
+                }),
+            }
+        }
+        counts
+    }
+
+    /// Note links across the folder that don't resolve to an existing note.
+    pub fn unresolved(&self, notes_root: &Path, layout: &NoteLayout) -> Vec<UnresolvedLink> {
+        let resolver = self.resolver(layout);
+        let mut result = Vec::new();
+        for source in &self.notes {
+            for link in self.links.get(source).into_iter().flatten() {
+                if resolver.resolve(notes_root, source, link) == Resolution::Unresolved {
+                    result.push(UnresolvedLink {
+                        source: source.clone(),
+                        target: link.target.clone(),
+                        kind: link.kind,
+                        line: link.line,
+                        column: link.column,
+                    });
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Resolves link targets against a set of note IDs.
+pub struct Resolver<'a> {
+    notes: &'a BTreeSet<String>,
+    layout: &'a NoteLayout,
+    by_lower: HashMap<String, &'a str>,
+    by_basename: HashMap<String, Vec<&'a str>>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(notes: &'a BTreeSet<String>, layout: &'a NoteLayout) -> Self {
+        let mut by_lower = HashMap::new();
+        let mut by_basename: HashMap<String, Vec<&str>> = HashMap::new();
+        for id in notes {
+            by_lower.insert(id.to_lowercase(), id.as_str());
+            let basename = id.rsplit('/').next().unwrap_or(id).to_lowercase();
+            by_basename.entry(basename).or_default().push(id.as_str());
+        }
+        Self {
+            notes,
+            layout,
+            by_lower,
+            by_basename,
+        }
+    }
+
+    pub fn resolve(&self, notes_root: &Path, source_id: &str, link: &Link) -> Resolution {
+        self.resolve_target(notes_root, source_id, &link.target, link.wiki_syntax)
+    }
+
+    /// Resolve a target written in `source_id`, without anchor or alias
+    /// (percent-decoded for markdown links).
+    pub fn resolve_target(&self, notes_root: &Path, source_id: &str, target: &str, wiki_syntax: bool) -> Resolution {
+        if target.is_empty() || (!wiki_syntax && is_external_target(target)) {
+            return Resolution::NotANote;
+        }
+        if !has_note_extension(self.layout, target) {
+            return Resolution::NotANote;
+        }
+        let found = if wiki_syntax {
+            self.resolve_wiki(source_id, target)
+        } else {
+            self.resolve_path(notes_root, source_id, target)
+        };
+        match found {
+            Some(id) => Resolution::Note(id),
+            None => Resolution::Unresolved,
+        }
+    }
+
+    /// Wiki targets match by path (relative to the source note, then the root),
+    /// then case-insensitively, then by unique file name anywhere in the folder.
+    /// A leading `/` skips the relative lookup.
+    fn resolve_wiki(&self, source_id: &str, target: &str) -> Option<String> {
+        let target = target.trim();
+        let name = strip_note_extension(self.layout, target.trim_start_matches('/'));
+        if !target.starts_with('/') && self.shadowed_by_relative(source_id, name) {
+            let dir = &source_id[..source_id.rfind('/').unwrap_or(0)];
+            return Some(format!("{}/{}", dir, name));
+        }
+        if self.notes.contains(name) {
+            return Some(name.to_string());
+        }
+        let lower = name.to_lowercase();
+        if let Some(id) = self.by_lower.get(&lower) {
+            return Some(id.to_string());
+        }
+        match self.by_basename.get(&lower).map(|ids| ids.as_slice()) {
+            Some([only]) => Some(only.to_string()),
+            Some(candidates) if !candidates.is_empty() => {
+                // Ambiguous: prefer the shortest path, like Obsidian
+                candidates
+                    .iter()
+                    .min_by_key(|id| (id.matches('/').count(), id.len()))
+                    .map(|id| id.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Markdown targets are paths relative to the source note's directory.
+    fn resolve_path(&self, notes_root: &Path, source_id: &str, target: &str) -> Option<String> {
+        let id = path_target_to_id(notes_root, self.layout, source_id, target)?;
+        self.notes.contains(&id).then_some(id)
+    }
+
+    /// Whether `[[name]]` written in `source_id` would resolve to a note next to the source.
+    fn shadowed_by_relative(&self, source_id: &str, name: &str) -> bool {
+        match source_id.rfind('/') {
+            Some(pos) => self.notes.contains(&format!("{}/{}", &source_id[..pos], name)),
+            None => false,
+        }
+    }
+
+    /// Whether `[[basename]]` would unambiguously resolve to `id`.
+    fn basename_points_to(&self, basename: &str, id: &str) -> bool {
+        match self.by_basename.get(&basename.to_lowercase()) {
+            None => true,
+            Some(ids) => ids.iter().all(|other| *other == id),
+        }
+    }
+}
+
+/// Rewrite links in `content` (a note with ID `source_id`) that resolve to `old_id`
+/// so they point at `new_id` instead, keeping each link's style (wiki name vs path,
+/// relative vs root-relative, extension, percent-encoding).
+/// Returns the new content and the number of rewritten links, or None if nothing changed.
+pub fn retarget_links(
+    content: &str,
+    notes_root: &Path,
+    source_id: &str,
+    resolver: &Resolver<'_>,
+    old_id: &str,
+    new_id: &str,
+) -> Option<(String, usize)> {
+    let mut edits = Vec::new();
+
+    for link in extract_links(content) {
+        if resolver.resolve(notes_root, source_id, &link) != Resolution::Note(old_id.to_string()) {
+            continue;
+        }
+        let raw = &content[link.target_span.0..link.target_span.1];
+        let replacement = if link.wiki_syntax {
+            let new_basename = new_id.rsplit('/').next().unwrap_or(new_id);
+            let mut target = if raw.contains('/') || !resolver.basename_points_to(new_basename, old_id) {
+                new_id.to_string()
+            } else {
+                new_basename.to_string()
+            };
+            if resolver.shadowed_by_relative(source_id, &target) {
+                target.insert(0, '/');
+            }
+            if let Some(extension) = note_extension_of(resolver.layout, raw.trim_end()) {
+                target.push('.');
+                target.push_str(extension);
+            }
+            target
+        } else {
+            let decoded = &link.target;
+            let extension = note_extension_of(resolver.layout, decoded)
+                .map(|e| format!(".{}", e))
+                .unwrap_or_default();
+            let path = if decoded.starts_with('/') {
+                format!("/{}{}", new_id, extension)
+            } else {
+                let source_dir = source_id.rfind('/').map(|pos| &source_id[..pos]).unwrap_or("");
+                format!("{}{}", relative_path(source_dir, new_id), extension)
+            };
+            let in_angle_brackets = content[..link.target_span.0].ends_with('<');
+            let was_encoded = raw != decoded.as_str();
+            if was_encoded || (!in_angle_brackets && path.contains([' ', '(', ')', '<', '>'])) {
+                encode_path(&path)
+            } else {
+                path
+            }
+        };
+        if replacement != raw {
+            edits.push((link.target_span.0, link.target_span.1, replacement));
+        }
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        let count = edits.len();
+        Some((markdown::apply_edits(content, edits), count))
+    }
+}
+
+/// Relative POSIX path from directory `from_dir` to `to` (both relative to the notes root).
+pub fn relative_path(from_dir: &str, to: &str) -> String {
+    let from: Vec<&str> = from_dir.split('/').filter(|p| !p.is_empty()).collect();
+    let to_parts: Vec<&str> = to.split('/').filter(|p| !p.is_empty()).collect();
+    let common = from
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts: Vec<&str> = vec![".."; from.len() - common];
+    parts.extend(&to_parts[common..]);
+    parts.join("/")
+}
+
+/// Percent-encode each segment of a POSIX path, keeping the separators.
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Convert a markdown link path into the note ID it would refer to, resolved
+/// relative to the source note and validated with `id_from_abs_path`.
+pub fn path_target_to_id(notes_root: &Path, layout: &NoteLayout, source_id: &str, target: &str) -> Option<String> {
+    let source_dir = source_id.rfind('/').map(|pos| &source_id[..pos]).unwrap_or("");
+    let rel = normalize_relative_path(source_dir, target)?;
+    // Names with dots (`meeting.2024-01-15`) get the extension too
+    let has_note_extension = Path::new(&rel)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| layout.is_note_extension(ext));
+    let rel = if has_note_extension {
+        rel
+    } else {
+        format!("{}.{}", rel, crate::naming::DEFAULT_EXTENSION)
+    };
+    crate::id_from_abs_path(notes_root, &notes_root.join(rel), layout)
+}
+
+/// Lexically join `target` onto `base_dir` (both POSIX, relative to the notes root),
+/// resolving `.` and `..`. A leading `/` means relative to the root.
+/// Returns None if the path escapes the root.
+pub fn normalize_relative_path(base_dir: &str, target: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    if !target.starts_with('/') {
+        parts.extend(base_dir.split('/').filter(|p| !p.is_empty()));
+    }
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            other => parts.push(other),
+        }
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("/"))
+    }
+}
+
+/// Whether a target is a URL (has a scheme) or protocol-relative.
+pub fn is_external_target(target: &str) -> bool {
+    if target.starts_with("//") {
+        return true;
+    }
+    match target.find(':') {
+        Some(pos) => {
+            let scheme = &target[..pos];
+            // Single letters are Windows drive letters, not schemes
+            scheme.len() > 1
+                && scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// Targets without an extension, or with a note extension (`.md`, `.markdown`, ...), may be notes.
+fn has_note_extension(layout: &NoteLayout, target: &str) -> bool {
+    let file_name = target.rsplit('/').next().unwrap_or(target);
+    match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => {
+            layout.is_note_extension(ext)
+                // Dotted note names like "meeting.2024-01-15" aren't extensions
+                || !ext.chars().all(|c| c.is_ascii_alphanumeric())
+                || ext.chars().all(|c| c.is_ascii_digit())
+        }
+        _ => true,
+    }
+}
+
+/// The note extension a link target ends with, if any.
+fn note_extension_of<'t>(layout: &NoteLayout, target: &'t str) -> Option<&'t str> {
+    let file_name = target.rsplit('/').next().unwrap_or(target);
+    file_name
+        .rsplit_once('.')
+        .filter(|(stem, ext)| !stem.is_empty() && layout.is_note_extension(ext))
+        .map(|(_, ext)| ext)
+}
+
+fn strip_note_extension<'t>(layout: &NoteLayout, name: &'t str) -> &'t str {
+    match note_extension_of(layout, name) {
+        Some(ext) => &name[..name.len() - ext.len() - 1],
+        None => name,
+    }
+}
+
+/// Extract wiki and markdown links from text lines (code blocks and inline code are skipped).
+pub fn extract_links(content: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+
+    for line in markdown::lines(content) {
+        if line.kind != LineKind::Text {
+            continue;
+        }
+        let masked = markdown::mask_inline_code(line.text);
+        let context: String = line.text.trim().chars().take(200).collect();
+        let column_of = |pos: usize| line.text[..pos].chars().count() + 1;
+
+        for caps in WIKI_LINK_RE.captures_iter(&masked) {
+            let whole = caps.get(0).unwrap();
+            let inner = caps.get(2).unwrap();
+            let embed = !caps[1].is_empty();
+            let raw = &line.text[inner.start()..inner.end()];
+            // [[target#anchor|alias]]
+            let target_part = raw.split('|').next().unwrap_or(raw);
+            let (target, anchor) = match target_part.split_once('#') {
+                Some((t, a)) => (t, Some(a.trim().to_string())),
+                None => (target_part, None),
+            };
+            let target_start = line.offset + inner.start();
+            links.push(Link {
+                kind: if embed { LinkKind::Embed } else { LinkKind::Wiki },
+                wiki_syntax: true,
+                target: target.trim().to_string(),
+                anchor,
+                line: line.number,
+                column: column_of(whole.start()),
+                span: (line.offset + whole.start(), line.offset + whole.end()),
+                target_span: (target_start, target_start + target.len()),
+                context: context.clone(),
+            });
+        }
+
+        for caps in MARKDOWN_LINK_RE.captures_iter(&masked) {
+            let whole = caps.get(0).unwrap();
+            // Skip the [[...]] pairs already handled as wiki links
+            if masked[whole.start()..].trim_start_matches('!').starts_with("[[") {
+                continue;
+            }
+            let embed = !caps[1].is_empty();
+            let dest = caps.get(3).unwrap();
+            let (dest_start, dest_end) = if masked[dest.start()..dest.end()].starts_with('<') {
+                (dest.start() + 1, dest.end() - 1)
+            } else {
+                (dest.start(), dest.end())
+            };
+            let raw = &line.text[dest_start..dest_end];
+            let (raw_target, anchor) = match raw.split_once('#') {
+                Some((t, a)) => (t, Some(urlencoding::decode(a).map(|s| s.into_owned()).unwrap_or_else(|_| a.to_string()))),
+                None => (raw, None),
+            };
+            let target = urlencoding::decode(raw_target)
+                .map(|s| s.into_owned())
+                .unwrap_or_else(|_| raw_target.to_string());
+            let target_start = line.offset + dest_start;
+            links.push(Link {
+                kind: if embed { LinkKind::Embed } else { LinkKind::Markdown },
+                wiki_syntax: false,
+                target,
+                anchor,
+                line: line.number,
+                column: column_of(whole.start()),
+                span: (line.offset + whole.start(), line.offset + whole.end()),
+                target_span: (target_start, target_start + raw_target.len()),
+                context: context.clone(),
+            });
+        }
+    }
+
+    links.sort_by_key(|link| link.span.0);
+    links
+}
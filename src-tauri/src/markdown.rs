@@ -0,0 +1,206 @@
+//! Line-level markdown scanning shared by the note indexes.
+
+/// What part of the document a line belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// Inside the leading YAML frontmatter block (delimiters included)
+    Frontmatter,
+    /// Inside a fenced code block (fences included)
+    Code,
+    /// Regular markdown text
+    Text,
+}
+
+/// A single line of a note, without its line terminator.
+#[derive(Debug, Clone, Copy)]
+pub struct Line<'a> {
+    /// 1-based line number
+    pub number: usize,
+    /// Byte offset of the line start within the whole content
+    pub offset: usize,
+    pub text: &'a str,
+    pub kind: LineKind,
+}
+
+/// Split content into classified lines, tracking frontmatter and fenced code blocks.
+pub fn lines(content: &str) -> Vec<Line<'_>> {
+    let mut result = Vec::new();
+    let mut offset = 0;
+    // (fence char, fence length) of the currently open code block
+    let mut fence: Option<(char, usize)> = None;
+    let mut in_frontmatter = false;
+
+    for (idx, raw) in content.split_inclusive('\n').enumerate() {
+        let text = raw
+            .strip_suffix('\n')
+            .map(|t| t.strip_suffix('\r').unwrap_or(t))
+            .unwrap_or(raw);
+        let line_offset = offset;
+        offset += raw.len();
+
+        let kind = if idx == 0 && text.trim_start_matches('\u{FEFF}').trim_end() == "---" {
+            in_frontmatter = has_frontmatter_close(&content[offset..]);
+            if in_frontmatter {
+                LineKind::Frontmatter
+            } else {
+                LineKind::Text
+            }
+        } else if in_frontmatter {
+            let trimmed = text.trim_end();
+            if trimmed == "---" || trimmed == "..." {
+                in_frontmatter = false;
+            }
+            LineKind::Frontmatter
+        } else if let Some((ch, len)) = fence {
+            if let Some((close_ch, close_len, rest)) = parse_fence(text) {
+                if close_ch == ch && close_len >= len && rest.trim().is_empty() {
+                    fence = None;
+                }
+            }
+            LineKind::Code
+        } else if let Some((ch, len, _)) = parse_fence(text) {
+            fence = Some((ch, len));
+            LineKind::Code
+        } else {
+            LineKind::Text
+        };
+
+        result.push(Line {
+            number: idx + 1,
+            offset: line_offset,
+            text,
+            kind,
+        });
+    }
+
+    result
+}
+
+/// Whether the remainder after an opening `---` contains a closing delimiter line.
+fn has_frontmatter_close(rest: &str) -> bool {
+    rest.lines().any(|l| {
+        let trimmed = l.trim_end();
+        trimmed == "---" || trimmed == "..."
+    })
+}
+
+/// Parse a code fence opener/closer: returns (fence char, run length, info string).
+fn parse_fence(line: &str) -> Option<(char, usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let ch = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = rest.chars().take_while(|c| *c == ch).count();
+    if len < 3 {
+        return None;
+    }
+    let info = &rest[len..];
+    // Backtick fences may not contain backticks in the info string
+    if ch == '`' && info.contains('`') {
+        return None;
+    }
+    Some((ch, len, info))
+}
+
+/// pulldown-cmark options for notes: GFM extensions plus YAML frontmatter and wikilinks.
+pub fn parser_options() -> pulldown_cmark::Options {
+    use pulldown_cmark::Options;
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_WIKILINKS
+}
+
+/// Whether a pulldown-cmark tag is inline formatting rather than a block.
+pub fn is_inline_tag(tag: pulldown_cmark::TagEnd) -> bool {
+    use pulldown_cmark::TagEnd;
+    matches!(
+        tag,
+        TagEnd::Emphasis
+            | TagEnd::Strong
+            | TagEnd::Strikethrough
+            | TagEnd::Superscript
+            | TagEnd::Subscript
+            | TagEnd::Link
+            | TagEnd::Image
+    )
+}
+
+/// GitHub-style heading anchor: lowercase, punctuation dropped, spaces as `-`.
+pub fn heading_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c)
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Replace inline code spans (backticks included) with spaces, preserving byte positions.
+pub fn mask_inline_code(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut masked = bytes.to_vec();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        let run = bytes[i..].iter().take_while(|b| **b == b'`').count();
+        // Look for a closing run of exactly the same length
+        let mut j = i + run;
+        let mut close = None;
+        while j < bytes.len() {
+            if bytes[j] == b'`' {
+                let close_run = bytes[j..].iter().take_while(|b| **b == b'`').count();
+                if close_run == run {
+                    close = Some(j + close_run);
+                    break;
+                }
+                j += close_run;
+            } else {
+                j += 1;
+            }
+        }
+        match close {
+            Some(end) => {
+                masked[i..end].fill(b' ');
+                i = end;
+            }
+            None => i += run,
+        }
+    }
+
+    // Only ASCII bytes were replaced by ASCII spaces, and whole spans at that,
+    // so multi-byte sequences are either fully kept or fully replaced.
+    String::from_utf8(masked).unwrap_or_else(|_| line.to_string())
+}
+
+/// Apply non-overlapping byte-range replacements to `content`.
+pub fn apply_edits(content: &str, mut edits: Vec<(usize, usize, String)>) -> String {
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut result = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for (start, end, replacement) in edits {
+        if start < cursor {
+            continue;
+        }
+        result.push_str(&content[cursor..start]);
+        result.push_str(&replacement);
+        cursor = end;
+    }
+    result.push_str(&content[cursor..]);
+    result
+}
@@ -0,0 +1,104 @@
+//! How note files are named: extensions, and names for new and renamed notes.
+
+use serde::{Deserialize, Serialize};
+
+/// Leaf name given to notes before they have a title.
+pub const PLACEHOLDER_NAME: &str = "Untitled";
+
+/// Extension new notes are created with.
+pub const DEFAULT_EXTENSION: &str = "md";
+
+/// Extensions always treated as notes, in lookup priority order.
+const BUILTIN_EXTENSIONS: &[&str] = &[DEFAULT_EXTENSION, "markdown"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilenameStrategy {
+    /// File name follows the title and is renamed when the title changes
+    #[default]
+    TitleSynced,
+    /// File name is taken from the first title and then kept
+    TitleFrozen,
+    /// Zettelkasten-style `YYYYMMDDHHMMSS` ID assigned on creation
+    Timestamp,
+    /// Lowercase ASCII slug of the title, renamed when the title changes
+    Slug,
+}
+
+impl FilenameStrategy {
+    /// File name (without extension) for a note with this title, ignoring collisions.
+    pub fn name_for_title(self, title: &str) -> String {
+        match self {
+            FilenameStrategy::TitleSynced | FilenameStrategy::TitleFrozen => crate::sanitize_filename(title),
+            FilenameStrategy::Timestamp => timestamp_id(),
+            FilenameStrategy::Slug => slugify(title),
+        }
+    }
+
+    /// The name an existing note should be renamed to when saved with this
+    /// title, or None to keep its current name. Frozen notes still take
+    /// their first real title while they have the placeholder name.
+    pub fn rename_target(self, current_leaf: &str, title: &str) -> Option<String> {
+        match self {
+            FilenameStrategy::TitleSynced | FilenameStrategy::Slug => Some(self.name_for_title(title)),
+            FilenameStrategy::TitleFrozen if is_placeholder(current_leaf) => Some(self.name_for_title(title)),
+            FilenameStrategy::TitleFrozen | FilenameStrategy::Timestamp => None,
+        }
+    }
+
+    /// File name (without extension) for a brand-new, empty note.
+    pub fn placeholder_name(self) -> String {
+        match self {
+            FilenameStrategy::Timestamp => timestamp_id(),
+            FilenameStrategy::Slug => slugify(PLACEHOLDER_NAME),
+            FilenameStrategy::TitleSynced | FilenameStrategy::TitleFrozen => PLACEHOLDER_NAME.to_string(),
+        }
+    }
+}
+
+/// Whether a leaf name is the placeholder, possibly with a `-N` counter.
+fn is_placeholder(leaf: &str) -> bool {
+    let base = match leaf.rsplit_once('-') {
+        Some((base, counter)) if !counter.is_empty() && counter.chars().all(|c| c.is_ascii_digit()) => base,
+        _ => leaf,
+    };
+    base.eq_ignore_ascii_case(PLACEHOLDER_NAME)
+}
+
+fn timestamp_id() -> String {
+    chrono::Local::now().format("%Y%m%d%H%M%S").to_string()
+}
+
+/// Lowercase ASCII slug: non-Latin text is transliterated, anything else
+/// becomes single dashes (`Café Notes: 2024` -> `cafe-notes-2024`).
+pub fn slugify(title: &str) -> String {
+    let ascii = deunicode::deunicode(title).to_lowercase();
+    let mut slug = String::with_capacity(ascii.len());
+    for c in ascii.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        PLACEHOLDER_NAME.to_lowercase()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// All note extensions, in lookup priority order (`md` first), given the
+/// extra extensions from settings. Extras are normalized (leading dot
+/// dropped, lowercased) and invalid ones ignored.
+pub fn note_extensions(extra: &[String]) -> Vec<String> {
+    let mut extensions: Vec<String> = BUILTIN_EXTENSIONS.iter().map(|ext| ext.to_string()).collect();
+    for ext in extra {
+        let ext = ext.trim().trim_start_matches('.').to_ascii_lowercase();
+        if !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()) && !extensions.contains(&ext) {
+            extensions.push(ext);
+        }
+    }
+    extensions
+}
@@ -0,0 +1,132 @@
+//! Heading outlines (tables of contents) and the per-folder outline cache.
+
+use crate::markdown;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineHeading {
+    /// 1 to 6
+    pub level: u8,
+    /// Heading text with formatting removed
+    pub text: String,
+    /// Anchor slug, with -1, -2, ... added to repeated slugs
+    pub slug: String,
+    /// Byte offset of the heading's first line within the note
+    pub offset: usize,
+    /// 1-based line number of the heading's first line
+    pub line: usize,
+    /// Headings nested under this one (deeper levels until the next heading at this level or above)
+    pub children: Vec<OutlineHeading>,
+}
+
+/// Per-folder outline cache: note ID -> heading tree.
+#[derive(Debug, Default)]
+pub struct OutlineIndex {
+    outlines: HashMap<String, Vec<OutlineHeading>>,
+}
+
+impl OutlineIndex {
+    pub fn update(&mut self, id: &str, content: &str) {
+        self.insert(id, parse_outline(content));
+    }
+
+    pub fn insert(&mut self, id: &str, outline: Vec<OutlineHeading>) {
+        self.outlines.insert(id.to_string(), outline);
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.outlines.remove(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.outlines.clear();
+    }
+
+    pub fn get(&self, id: &str) -> Option<Vec<OutlineHeading>> {
+        self.outlines.get(id).cloned()
+    }
+}
+
+/// Heading tree of a note. Handles ATX and Setext headings and ignores
+/// `#` lines inside code blocks and frontmatter.
+pub fn parse_outline(content: &str) -> Vec<OutlineHeading> {
+    let mut flat: Vec<OutlineHeading> = Vec::new();
+    let mut current: Option<(u8, usize, String)> = None;
+    let mut slug_counts: HashMap<String, usize> = HashMap::new();
+    // Line counting resumes from the previous heading, since offsets only grow
+    let (mut line, mut counted_to) = (1, 0);
+
+    for (event, range) in Parser::new_ext(content, markdown::parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                current = Some((level as u8, range.start, String::new()));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((level, offset, text)) = current.take() else {
+                    continue;
+                };
+                line += content[counted_to..offset].matches('\n').count();
+                counted_to = offset;
+
+                let text = text.trim().to_string();
+                let slug = markdown::heading_slug(&text);
+                let count = slug_counts.entry(slug.clone()).or_insert(0);
+                let slug = if *count == 0 {
+                    slug
+                } else {
+                    format!("{}-{}", slug, count)
+                };
+                *count += 1;
+
+                flat.push(OutlineHeading {
+                    level,
+                    text,
+                    slug,
+                    offset,
+                    line,
+                    children: Vec::new(),
+                });
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, ref mut heading_text)) = current {
+                    heading_text.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some((_, _, ref mut heading_text)) = current {
+                    heading_text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    nest(flat)
+}
+
+/// Turn headings in document order into a tree by level.
+fn nest(flat: Vec<OutlineHeading>) -> Vec<OutlineHeading> {
+    fn attach(stack: &mut [OutlineHeading], roots: &mut Vec<OutlineHeading>, heading: OutlineHeading) {
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(heading),
+            None => roots.push(heading),
+        }
+    }
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<OutlineHeading> = Vec::new();
+    for heading in flat {
+        while stack.last().is_some_and(|open| open.level >= heading.level) {
+            let done = stack.pop().expect("stack is non-empty");
+            attach(&mut stack, &mut roots, done);
+        }
+        stack.push(heading);
+    }
+    while let Some(done) = stack.pop() {
+        attach(&mut stack, &mut roots, done);
+    }
+    roots
+}
@@ -0,0 +1,297 @@
+//! Note titles and list previews, taken from the plain text of the parsed markdown.
+
+use crate::markdown;
+use pulldown_cmark::{Event, HeadingLevel, Parser, TagEnd};
+
+/// Longest title taken from a line that isn't a level-1 heading.
+const MAX_TITLE_CHARS: usize = 50;
+
+/// Longest preview, in characters.
+const MAX_PREVIEW_CHARS: usize = 200;
+
+/// One line of rendered text.
+struct PlainLine {
+    text: String,
+    /// Whether the line is a level-1 heading (ATX `#` or Setext `===`)
+    is_title: bool,
+}
+
+/// The text of a note as it would read rendered, one entry per line of a
+/// paragraph, heading or list item. Formatting markers, link targets and task
+/// boxes are dropped (image alt text is kept); frontmatter, code blocks, HTML
+/// and tables are skipped entirely. Empty lines are left out.
+fn plain_lines(content: &str) -> impl Iterator<Item = PlainLine> + '_ {
+    let mut events = Parser::new_ext(content, markdown::parser_options());
+    // Depth inside skipped blocks
+    let mut skip = 0usize;
+    let mut heading: Option<HeadingLevel> = None;
+    let mut current = String::new();
+
+    std::iter::from_fn(move || loop {
+        let Some(event) = events.next() else {
+            return take_line(&mut current, false);
+        };
+        let boundary = match event {
+            Event::Start(tag) => {
+                let tag = TagEnd::from(tag);
+                if is_skipped(tag) {
+                    skip += 1;
+                }
+                if let TagEnd::Heading(level) = tag {
+                    let previous = take_line(&mut current, false);
+                    heading = Some(level);
+                    if previous.is_some() {
+                        return previous;
+                    }
+                }
+                !markdown::is_inline_tag(tag)
+            }
+            Event::End(tag) => {
+                if is_skipped(tag) {
+                    skip = skip.saturating_sub(1);
+                }
+                let is_title = matches!(tag, TagEnd::Heading(HeadingLevel::H1));
+                if matches!(tag, TagEnd::Heading(_)) {
+                    heading = None;
+                    if let Some(line) = take_line(&mut current, is_title) {
+                        return Some(line);
+                    }
+                }
+                !markdown::is_inline_tag(tag)
+            }
+            Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => {
+                if skip == 0 {
+                    current.push_str(&text);
+                }
+                false
+            }
+            // Headings continue across soft breaks; other blocks start a new line
+            Event::SoftBreak if heading.is_some() => {
+                current.push(' ');
+                false
+            }
+            Event::SoftBreak | Event::HardBreak | Event::Rule => true,
+            _ => false,
+        };
+        if boundary && heading.is_none() {
+            if let Some(line) = take_line(&mut current, false) {
+                return Some(line);
+            }
+        }
+    })
+}
+
+/// Finish the line being built, or None if it has no visible text.
+fn take_line(current: &mut String, is_title: bool) -> Option<PlainLine> {
+    let text = std::mem::take(current);
+    let text = text.trim();
+    if crate::is_effectively_empty(text) {
+        return None;
+    }
+    Some(PlainLine {
+        text: text.to_string(),
+        is_title,
+    })
+}
+
+/// Blocks whose contents never show up in titles or previews.
+fn is_skipped(tag: TagEnd) -> bool {
+    matches!(
+        tag,
+        TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::Table | TagEnd::MetadataBlock(_)
+    )
+}
+
+/// Title of a note: its first line of text, in full if it is a level-1
+/// heading and cut to 50 characters otherwise.
+///
+/// The text is taken as rendered, so a first line of `## Foo` gives `Foo`
+/// and `# **Bold**` gives `Bold`. Titles used to keep such markers; notes
+/// already named that way aren't renamed for it (see `legacy_title`).
+pub fn extract_title(content: &str) -> String {
+    match plain_lines(content).next() {
+        Some(line) if line.is_title => line.text,
+        Some(line) => line.text.chars().take(MAX_TITLE_CHARS).collect(),
+        None => "Untitled".to_string(),
+    }
+}
+
+/// Title under the rules used before titles came from the parsed markdown:
+/// the first non-empty line after the frontmatter, with a leading `# `
+/// removed and any other markers kept. Notes named after it keep their name
+/// when saved, so upgrading doesn't rename them.
+pub fn legacy_title(content: &str) -> String {
+    let trimmed = content.trim_start();
+    let body = trimmed
+        .strip_prefix("---")
+        .and_then(|rest| {
+            let end = rest.find("\n---")?;
+            let after_close = &rest[end + 4..];
+            Some(
+                after_close
+                    .strip_prefix("\r\n")
+                    .or_else(|| after_close.strip_prefix('\n'))
+                    .unwrap_or(after_close),
+            )
+        })
+        .unwrap_or(content);
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if let Some(title) = trimmed.strip_prefix("# ") {
+            let title = title.trim();
+            if !crate::is_effectively_empty(title) {
+                return title.to_string();
+            }
+        }
+        if !crate::is_effectively_empty(trimmed) {
+            return trimmed.chars().take(MAX_TITLE_CHARS).collect();
+        }
+    }
+    "Untitled".to_string()
+}
+
+/// Preview of a note for lists and search results: the plain text after the
+/// title line, joined with spaces and cut to 200 characters.
+pub fn generate_preview(content: &str) -> String {
+    let mut preview = String::new();
+    for line in plain_lines(content).skip(1) {
+        if !preview.is_empty() {
+            preview.push(' ');
+        }
+        preview.push_str(&line.text);
+        if preview.len() >= MAX_PREVIEW_CHARS {
+            break;
+        }
+    }
+    preview.chars().take(MAX_PREVIEW_CHARS).collect()
+}
+
+/// All the plain text of a note, one line per paragraph line, heading or list item.
+pub fn plain_text(content: &str) -> String {
+    plain_lines(content).map(|line| line.text).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atx_title() {
+        assert_eq!(extract_title("# Shopping list\n\n- milk\n"), "Shopping list");
+    }
+
+    #[test]
+    fn setext_titles() {
+        assert_eq!(extract_title("Shopping list\n=============\n\nmilk\n"), "Shopping list");
+        assert_eq!(generate_preview("Shopping list\n=============\n\nmilk\n"), "milk");
+        // A level-2 Setext heading is a heading too, not a title
+        assert_eq!(extract_title("Groceries\n---------\n"), "Groceries");
+    }
+
+    #[test]
+    fn formatted_titles_are_plain_text() {
+        assert_eq!(extract_title("# **Bold** and _italic_ `code`\n"), "Bold and italic code");
+        assert_eq!(extract_title("# [Link](https://example.com) title\n"), "Link title");
+    }
+
+    #[test]
+    fn lower_headings_lose_their_markers() {
+        assert_eq!(extract_title("## Meeting notes\n\ntext\n"), "Meeting notes");
+        // Notes named under the old rules are matched against these
+        assert_eq!(legacy_title("## Meeting notes\n\ntext\n"), "## Meeting notes");
+        assert_eq!(legacy_title("---\ntags: [a]\n---\n# **Plan**\n"), "**Plan**");
+    }
+
+    #[test]
+    fn untitled_notes() {
+        assert_eq!(extract_title(""), "Untitled");
+        assert_eq!(extract_title("\n\u{00A0}\n"), "Untitled");
+        assert_eq!(extract_title("---\ntags: [a]\n---\n"), "Untitled");
+    }
+
+    #[test]
+    fn long_first_lines_are_cut() {
+        let line = "word ".repeat(20);
+        assert_eq!(extract_title(&line).chars().count(), MAX_TITLE_CHARS);
+        let heading = format!("# {}", line);
+        assert_eq!(extract_title(&heading), line.trim());
+    }
+
+    #[test]
+    fn snake_case_keeps_underscores() {
+        let content = "# Config\n\nSet max_retry_count and _emphasis_ in user_settings.\n";
+        assert_eq!(extract_title("my_snake_case_title\n"), "my_snake_case_title");
+        assert_eq!(generate_preview(content), "Set max_retry_count and emphasis in user_settings.");
+    }
+
+    #[test]
+    fn code_fences_are_skipped() {
+        let content = "# Snippet\n\n```rust\nfn main() {}\n```\n\nAfter the code.\n";
+        assert_eq!(generate_preview(content), "After the code.");
+        assert_eq!(extract_title("```\ncode first\n```\n\nReal title\n"), "Real title");
+        assert_eq!(generate_preview("# T\n\n    indented code\n\ntext\n"), "text");
+    }
+
+    #[test]
+    fn html_blocks_are_skipped() {
+        let content = "# Page\n\n<div class=\"note\">\nhidden markup\n</div>\n\nVisible text.\n";
+        assert_eq!(generate_preview(content), "Visible text.");
+    }
+
+    #[test]
+    fn tables_are_skipped() {
+        let content = "# Table\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\nBelow the table.\n";
+        assert_eq!(generate_preview(content), "Below the table.");
+    }
+
+    #[test]
+    fn frontmatter_is_skipped() {
+        let content = "---\ntitle: Ignored\ntags: [x]\n---\n# Real title\n\nBody text.\n";
+        assert_eq!(extract_title(content), "Real title");
+        assert_eq!(generate_preview(content), "Body text.");
+        let crlf = "---\r\ntitle: Ignored\r\n---\r\n# Real title\r\n\r\nBody text.\r\n";
+        assert_eq!(extract_title(crlf), "Real title");
+        assert_eq!(generate_preview(crlf), "Body text.");
+    }
+
+    #[test]
+    fn preview_joins_lines_and_drops_markup() {
+        let content = "# T\n\n- [ ] task one\n- [x] ~~done~~\n\n> quoted **text**\n\n![alt text](img.png) see [docs](https://x.y)\n";
+        assert_eq!(generate_preview(content), "task one done quoted text alt text see docs");
+    }
+
+    #[test]
+    fn preview_is_cut() {
+        let content = format!("# T\n\n{}\n", "lorem ipsum ".repeat(40));
+        assert_eq!(generate_preview(&content).chars().count(), MAX_PREVIEW_CHARS);
+    }
+
+    /// Golden corpus in `tests/fixtures/preview`: each `NAME.md` is checked
+    /// against `NAME.expected`, which holds a `title:` and a `preview:` line.
+    #[test]
+    fn golden_corpus() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/preview");
+        let mut fixtures: Vec<_> = std::fs::read_dir(&dir)
+            .expect("fixture folder")
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .collect();
+        fixtures.sort();
+        assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+
+        for fixture in fixtures {
+            let content = std::fs::read_to_string(&fixture).unwrap();
+            let expected = std::fs::read_to_string(fixture.with_extension("expected")).unwrap();
+            let field = |name: &str| {
+                expected
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                    .map(str::trim)
+                    .unwrap_or_else(|| panic!("{} missing from {}.expected", name, fixture.display()))
+            };
+            assert_eq!(extract_title(&content), field("title"), "title of {}", fixture.display());
+            assert_eq!(generate_preview(&content), field("preview"), "preview of {}", fixture.display());
+        }
+    }
+}
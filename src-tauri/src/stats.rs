@@ -0,0 +1,221 @@
+//! Document statistics (word counts, reading time) and the per-folder stats index.
+
+use crate::{cjk, markdown};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Reading speed for space-separated words, per minute.
+const WORDS_PER_MINUTE: f64 = 200.0;
+
+/// Reading speed for CJK characters, per minute.
+const CJK_CHARS_PER_MINUTE: f64 = 300.0;
+
+/// How many notes `VaultStats::largest_notes` lists.
+const LARGEST_NOTES: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteStats {
+    /// Space-separated words plus CJK characters, each counted as a word
+    pub words: usize,
+    /// CJK characters counted in `words`
+    pub cjk_characters: usize,
+    /// Non-whitespace characters of visible text
+    pub characters: usize,
+    pub headings: usize,
+    /// Links and wikilinks, not counting images
+    pub links: usize,
+    pub images: usize,
+    pub tasks: usize,
+    pub completed_tasks: usize,
+    /// Estimated reading time, rounded up
+    pub reading_minutes: usize,
+}
+
+impl NoteStats {
+    fn add(&mut self, other: &NoteStats) {
+        self.words += other.words;
+        self.cjk_characters += other.cjk_characters;
+        self.characters += other.characters;
+        self.headings += other.headings;
+        self.links += other.links;
+        self.images += other.images;
+        self.tasks += other.tasks;
+        self.completed_tasks += other.completed_tasks;
+        self.reading_minutes = reading_minutes(self.words, self.cjk_characters);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteSize {
+    pub id: String,
+    /// Bytes on disk
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayActivity {
+    /// Local day in `YYYY-MM-DD` form
+    pub date: String,
+    pub created: usize,
+    pub modified: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStats {
+    pub note_count: usize,
+    /// Sums over all notes
+    pub totals: NoteStats,
+    /// Bytes on disk of all notes
+    pub total_size: u64,
+    /// Largest notes first
+    pub largest_notes: Vec<NoteSize>,
+    /// Notes created and last modified per day, oldest first
+    pub activity: Vec<DayActivity>,
+}
+
+/// Per-folder stats index: note ID -> stats of that note.
+#[derive(Debug, Default)]
+pub struct StatsIndex {
+    note_stats: HashMap<String, NoteStats>,
+}
+
+impl StatsIndex {
+    pub fn update(&mut self, id: &str, content: &str) {
+        self.insert(id, note_stats(content));
+    }
+
+    pub fn insert(&mut self, id: &str, stats: NoteStats) {
+        self.note_stats.insert(id.to_string(), stats);
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.note_stats.remove(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.note_stats.clear();
+    }
+
+    pub fn get(&self, id: &str) -> Option<NoteStats> {
+        self.note_stats.get(id).copied()
+    }
+}
+
+fn reading_minutes(words: usize, cjk_characters: usize) -> usize {
+    let minutes = (words - cjk_characters) as f64 / WORDS_PER_MINUTE + cjk_characters as f64 / CJK_CHARS_PER_MINUTE;
+    minutes.ceil() as usize
+}
+
+/// Statistics of a note. Frontmatter, code blocks and HTML don't count
+/// towards words and characters; inline code does.
+pub fn note_stats(content: &str) -> NoteStats {
+    let mut stats = NoteStats::default();
+    let mut text = String::new();
+    // Depth inside blocks whose text isn't counted
+    let mut skip = 0usize;
+
+    for event in Parser::new_ext(content, markdown::parser_options()) {
+        match event {
+            Event::Start(tag) => {
+                match tag {
+                    Tag::Heading { .. } => stats.headings += 1,
+                    Tag::Link { .. } => stats.links += 1,
+                    Tag::Image { .. } => stats.images += 1,
+                    Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_) => skip += 1,
+                    _ => {}
+                }
+            }
+            Event::End(tag) => {
+                if matches!(tag, TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_)) {
+                    skip = skip.saturating_sub(1);
+                }
+                // Keep words in adjacent blocks apart
+                if !markdown::is_inline_tag(tag) {
+                    text.push('\n');
+                }
+            }
+            Event::Text(t) | Event::Code(t) if skip == 0 => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::TaskListMarker(done) => {
+                stats.tasks += 1;
+                if done {
+                    stats.completed_tasks += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for token in text.split(|c: char| c.is_whitespace() || cjk::is_cjk(c)) {
+        if token.chars().any(char::is_alphanumeric) {
+            stats.words += 1;
+        }
+    }
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        stats.characters += 1;
+        if cjk::is_cjk(c) && c.is_alphanumeric() {
+            stats.cjk_characters += 1;
+        }
+    }
+    stats.words += stats.cjk_characters;
+    stats.reading_minutes = reading_minutes(stats.words, stats.cjk_characters);
+    stats
+}
+
+/// File facts gathered for vault stats.
+pub struct NoteFile {
+    pub id: String,
+    pub size: u64,
+    /// Unix seconds; None where the file system doesn't record creation time
+    pub created: Option<i64>,
+    pub modified: i64,
+}
+
+/// Aggregate the indexed stats of `files` (notes missing from the index count
+/// as empty) and their file sizes and dates.
+pub fn vault_stats(index: &StatsIndex, files: &[NoteFile]) -> VaultStats {
+    let mut stats = VaultStats {
+        note_count: files.len(),
+        ..Default::default()
+    };
+    let mut days: HashMap<String, DayActivity> = HashMap::new();
+
+    for file in files {
+        if let Some(note) = index.get(&file.id) {
+            stats.totals.add(&note);
+        }
+        stats.total_size += file.size;
+        if let Some(date) = file.created.and_then(local_day) {
+            days.entry(date.clone()).or_insert_with(|| DayActivity { date, ..Default::default() }).created += 1;
+        }
+        if let Some(date) = local_day(file.modified) {
+            days.entry(date.clone()).or_insert_with(|| DayActivity { date, ..Default::default() }).modified += 1;
+        }
+    }
+
+    let mut by_size: Vec<&NoteFile> = files.iter().collect();
+    by_size.sort_by(|a, b| b.size.cmp(&a.size).then(a.id.cmp(&b.id)));
+    stats.largest_notes = by_size
+        .into_iter()
+        .take(LARGEST_NOTES)
+        .map(|f| NoteSize {
+            id: f.id.clone(),
+            size: f.size,
+        })
+        .collect();
+
+    let mut activity: Vec<DayActivity> = days.into_values().collect();
+    activity.sort_by(|a, b| a.date.cmp(&b.date));
+    stats.activity = activity;
+    stats
+}
+
+/// Local `YYYY-MM-DD` day of a Unix timestamp.
+fn local_day(secs: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(secs, 0).map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+}
@@ -0,0 +1,310 @@
+//! Tag extraction (inline `#tags` and frontmatter `tags:`) and the per-folder tag index.
+
+use crate::markdown::{self, LineKind};
+use pulldown_cmark::{Event, LinkType, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+
+// Tag with the number of notes using it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Per-folder tag index: note ID -> tags used in that note.
+#[derive(Debug, Default)]
+pub struct TagIndex {
+    note_tags: HashMap<String, BTreeSet<String>>,
+}
+
+impl TagIndex {
+    pub fn update(&mut self, id: &str, content: &str) {
+        let tags: BTreeSet<String> = extract_tags(content).into_iter().collect();
+        if tags.is_empty() {
+            self.note_tags.remove(id);
+        } else {
+            self.note_tags.insert(id.to_string(), tags);
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.note_tags.remove(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.note_tags.clear();
+    }
+
+    /// Tags of a single note, sorted.
+    pub fn tags_of(&self, id: &str) -> Vec<String> {
+        self.note_tags
+            .get(id)
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// All tags with note counts, sorted by tag name.
+    pub fn counts(&self) -> Vec<TagCount> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for tags in self.note_tags.values() {
+            for tag in tags {
+                *counts.entry(tag.as_str()).or_insert(0) += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(tag, count)| TagCount {
+                tag: tag.to_string(),
+                count,
+            })
+            .collect()
+    }
+
+    /// IDs of notes tagged with `tag` or any nested tag below it (`tag/child`), sorted.
+    pub fn notes_with(&self, tag: &str) -> Vec<String> {
+        let wanted = normalize_tag(tag);
+        let prefix = format!("{}/", wanted);
+        let mut ids: Vec<String> = self
+            .note_tags
+            .iter()
+            .filter(|(_, tags)| tags.iter().any(|t| *t == wanted || t.starts_with(&prefix)))
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+}
+
+/// Normalize a tag for comparison: strip a leading `#` and trailing `/`, lowercase.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim()
+        .trim_start_matches('#')
+        .trim_end_matches('/')
+        .to_lowercase()
+}
+
+/// Whether `tag` (without `#`) is a valid tag name.
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.chars().all(is_tag_char)
+        && !tag.chars().all(|c| c.is_ascii_digit() || c == '/')
+        && !tag.starts_with('/')
+        && !tag.ends_with('/')
+        && !tag.contains("//")
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '/'
+}
+
+/// Extract all tags from a note: frontmatter `tags:` plus inline `#tags` outside code and headings.
+/// Tags are normalized (lowercase, no `#`) and deduplicated in first-seen order.
+pub fn extract_tags(content: &str) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut tags = Vec::new();
+    let mut push = |tag: &str| {
+        let normalized = normalize_tag(tag);
+        if is_valid_tag(&normalized) && seen.insert(normalized.clone()) {
+            tags.push(normalized);
+        }
+    };
+
+    for span in frontmatter_tag_spans(content) {
+        push(&content[span.0..span.1]);
+    }
+    for span in inline_tag_spans(content) {
+        // Skip the leading '#'
+        push(&content[span.0 + 1..span.1]);
+    }
+
+    tags
+}
+
+/// Byte ranges of inline `#tag` tokens (including the `#`) in the text of the note
+/// as it renders. Headings, code, HTML, frontmatter and link destinations
+/// (`[see](#section)`, wiki link targets, autolinks) are skipped.
+pub fn inline_tag_spans(content: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    // For each open element, whether its text can't hold tags
+    let mut open: Vec<bool> = Vec::new();
+    // Adjacent text events, as one source range
+    let mut run: Option<Range<usize>> = None;
+
+    for (event, range) in Parser::new_ext(content, markdown::parser_options()).into_offset_iter() {
+        match event {
+            // Escapes and entities don't map back to the source; they end a run
+            Event::Text(text) if !open.contains(&true) && content.get(range.clone()) == Some(&*text) => {
+                run = match run.take() {
+                    Some(current) if current.end == range.start => Some(current.start..range.end),
+                    previous => {
+                        if let Some(previous) = previous {
+                            scan_tags(content, previous, &mut spans);
+                        }
+                        Some(range)
+                    }
+                };
+                continue;
+            }
+            Event::Start(tag) => open.push(hides_tags(&tag)),
+            Event::End(_) => {
+                open.pop();
+            }
+            _ => {}
+        }
+        if let Some(current) = run.take() {
+            scan_tags(content, current, &mut spans);
+        }
+    }
+    if let Some(current) = run {
+        scan_tags(content, current, &mut spans);
+    }
+
+    spans
+}
+
+/// Elements whose text is never scanned for tags.
+fn hides_tags(tag: &Tag) -> bool {
+    match tag {
+        Tag::Heading { .. } | Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_) => true,
+        Tag::Link { link_type, .. } => {
+            matches!(link_type, LinkType::WikiLink { .. } | LinkType::Autolink | LinkType::Email)
+        }
+        _ => false,
+    }
+}
+
+/// Find `#tag` tokens in `content[range]`, a run of plain text.
+fn scan_tags(content: &str, range: Range<usize>, spans: &mut Vec<(usize, usize)>) {
+    let text = &content[range.clone()];
+    let mut prev = content[..range.start].chars().next_back();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((pos, c)) = chars.next() {
+        let at_boundary = prev.is_none_or(|p| p.is_whitespace() || p == '(' || p == '[');
+        prev = Some(c);
+        if c != '#' || !at_boundary {
+            continue;
+        }
+        let start = pos + 1;
+        let mut end = start;
+        while let Some(&(next_pos, next)) = chars.peek() {
+            if !is_tag_char(next) {
+                break;
+            }
+            end = next_pos + next.len_utf8();
+            prev = Some(next);
+            chars.next();
+        }
+        let tag = text[start..end].trim_end_matches('/');
+        if is_valid_tag(tag) {
+            spans.push((range.start + pos, range.start + start + tag.len()));
+        }
+    }
+}
+
+/// Byte ranges of tag values listed under a `tags:` (or `tag:`) frontmatter key,
+/// excluding any leading `#`. Supports inline lists (`tags: [a, b]`, `tags: a, b`)
+/// and block lists (`- a`).
+fn frontmatter_tag_spans(content: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut in_tags_block = false;
+
+    for line in markdown::lines(content) {
+        if line.kind != LineKind::Frontmatter {
+            if line.number > 1 {
+                break;
+            }
+            continue;
+        }
+        let text = line.text;
+        let is_indented = text.starts_with(' ') || text.starts_with('\t');
+
+        if !is_indented {
+            in_tags_block = false;
+            let Some((key, value)) = text.split_once(':') else {
+                continue;
+            };
+            if key.trim() != "tags" && key.trim() != "tag" {
+                continue;
+            }
+            let value_offset = line.offset + (text.len() - value.len());
+            if value.trim().is_empty() {
+                in_tags_block = true;
+            } else {
+                collect_value_spans(value, value_offset, &mut spans);
+            }
+        } else if in_tags_block {
+            let trimmed = text.trim_start();
+            if let Some(item) = trimmed.strip_prefix('-') {
+                let item_offset = line.offset + (text.len() - item.len());
+                collect_value_spans(item, item_offset, &mut spans);
+            }
+        }
+    }
+
+    spans
+}
+
+/// Split a YAML scalar or flow list into tag tokens, recording absolute byte ranges.
+fn collect_value_spans(value: &str, base: usize, spans: &mut Vec<(usize, usize)>) {
+    let is_separator = |c: char| c.is_whitespace() || matches!(c, ',' | '[' | ']' | '"' | '\'');
+    let mut start: Option<usize> = None;
+
+    for (pos, c) in value.char_indices().chain(std::iter::once((value.len(), ' '))) {
+        if is_separator(c) {
+            if let Some(s) = start.take() {
+                let token = &value[s..pos];
+                let token_start = s + (token.len() - token.trim_start_matches('#').len());
+                if token_start < pos {
+                    spans.push((base + token_start, base + pos));
+                }
+            }
+        } else if start.is_none() {
+            start = Some(pos);
+        }
+    }
+}
+
+/// Map a tag onto its renamed form if it is `old` or nested below it.
+fn renamed_tag(tag: &str, old: &str, new: &str) -> Option<String> {
+    let normalized = tag.to_lowercase();
+    if normalized == old {
+        Some(new.to_string())
+    } else {
+        let rest = normalized.strip_prefix(old).filter(|rest| rest.starts_with('/'))?;
+        // Keep the original casing of nested segments when byte lengths allow it
+        let rest = if normalized.len() == tag.len() {
+            &tag[old.len()..]
+        } else {
+            rest
+        };
+        Some(format!("{}{}", new, rest))
+    }
+}
+
+/// Rewrite every occurrence of tag `old` (and its nested tags) to `new`.
+/// Returns None if the content doesn't use the tag.
+pub fn rename_tag_in_content(content: &str, old: &str, new: &str) -> Option<String> {
+    let old = normalize_tag(old);
+    let new = new.trim().trim_start_matches('#').trim_end_matches('/').to_string();
+    let mut edits = Vec::new();
+
+    for (start, end) in frontmatter_tag_spans(content) {
+        if let Some(replacement) = renamed_tag(&content[start..end], &old, &new) {
+            edits.push((start, end, replacement));
+        }
+    }
+    for (start, end) in inline_tag_spans(content) {
+        if let Some(replacement) = renamed_tag(&content[start + 1..end], &old, &new) {
+            edits.push((start + 1, end, replacement));
+        }
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        Some(markdown::apply_edits(content, edits))
+    }
+}
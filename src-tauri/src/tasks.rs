@@ -0,0 +1,161 @@
+//! GFM task list items (`- [ ]` / `- [x]`) and the per-folder task index.
+
+use crate::markdown::{self, LineKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// List marker (after any blockquote markers), checkbox and text.
+static TASK_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"^[ \t]*(?:>[ \t]*)*(?:[-+*]|\d{1,9}[.)])[ \t]+\[([ xX])\][ \t]+(\S.*)$").unwrap());
+
+/// Due dates: `due:2024-05-01`, `@2024-05-01` or `📅 2024-05-01`.
+static DUE_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(?:^|\s)(?:due:\s*|@|📅\s*)(\d{4}-\d{2}-\d{2})\b").unwrap());
+
+/// Priorities: `priority:high`, `!high` (also medium/low) or the ⏫ 🔼 🔽 markers.
+static PRIORITY_RE: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"(?i)(?:^|\s)(?:priority:\s*|!)(high|medium|low)\b|(⏫|🔼|🔽)").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskPriority {
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    pub note_id: String,
+    /// 1-based line number
+    pub line: usize,
+    /// Text after the checkbox, annotations included
+    pub text: String,
+    pub completed: bool,
+    /// `YYYY-MM-DD`
+    pub due: Option<String>,
+    pub priority: Option<TaskPriority>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskFilter {
+    pub completed: Option<bool>,
+    pub note_id: Option<String>,
+    /// Only tasks due on or before this day (`YYYY-MM-DD`); tasks without a due date are excluded
+    pub due_before: Option<String>,
+    /// Only tasks due on or after this day (`YYYY-MM-DD`); tasks without a due date are excluded
+    pub due_after: Option<String>,
+    pub priority: Option<TaskPriority>,
+    /// Case-insensitive substring of the task text
+    pub query: Option<String>,
+}
+
+impl TaskFilter {
+    fn matches(&self, task: &Task) -> bool {
+        let due = task.due.as_deref();
+        self.completed.is_none_or(|c| c == task.completed)
+            && self.note_id.as_ref().is_none_or(|id| *id == task.note_id)
+            && self.due_before.as_deref().is_none_or(|d| due.is_some_and(|due| due <= d))
+            && self.due_after.as_deref().is_none_or(|d| due.is_some_and(|due| due >= d))
+            && self.priority.is_none_or(|p| task.priority == Some(p))
+            && self
+                .query
+                .as_ref()
+                .is_none_or(|q| task.text.to_lowercase().contains(&q.to_lowercase()))
+    }
+}
+
+/// Per-folder task index: note ID -> tasks in that note, in line order.
+#[derive(Debug, Default)]
+pub struct TaskIndex {
+    note_tasks: HashMap<String, Vec<Task>>,
+}
+
+impl TaskIndex {
+    pub fn update(&mut self, id: &str, content: &str) {
+        let tasks = extract_tasks(id, content);
+        if tasks.is_empty() {
+            self.note_tasks.remove(id);
+        } else {
+            self.note_tasks.insert(id.to_string(), tasks);
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.note_tasks.remove(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.note_tasks.clear();
+    }
+
+    /// Tasks matching `filter`, sorted by note ID and line.
+    pub fn list(&self, filter: &TaskFilter) -> Vec<Task> {
+        let mut tasks: Vec<Task> = self
+            .note_tasks
+            .values()
+            .flatten()
+            .filter(|task| filter.matches(task))
+            .cloned()
+            .collect();
+        tasks.sort_by(|a, b| a.note_id.cmp(&b.note_id).then(a.line.cmp(&b.line)));
+        tasks
+    }
+}
+
+/// A task item found on a line: the task and the byte offset of its checkbox state character.
+fn parse_task_line(note_id: &str, line: &markdown::Line) -> Option<(Task, usize)> {
+    if line.kind != LineKind::Text {
+        return None;
+    }
+    let caps = TASK_RE.captures(line.text)?;
+    let state = caps.get(1)?;
+    let text = caps[2].trim();
+    let priority = PRIORITY_RE.captures(text).and_then(|p| {
+        let word = p.get(1).map(|m| m.as_str().to_lowercase());
+        match (word.as_deref(), p.get(2).map(|m| m.as_str())) {
+            (Some("high"), _) | (_, Some("⏫")) => Some(TaskPriority::High),
+            (Some("medium"), _) | (_, Some("🔼")) => Some(TaskPriority::Medium),
+            (Some("low"), _) | (_, Some("🔽")) => Some(TaskPriority::Low),
+            _ => None,
+        }
+    });
+    let task = Task {
+        note_id: note_id.to_string(),
+        line: line.number,
+        text: text.to_string(),
+        completed: state.as_str() != " ",
+        due: DUE_RE.captures(text).map(|d| d[1].to_string()),
+        priority,
+    };
+    Some((task, line.offset + state.start()))
+}
+
+/// All task items in a note, outside frontmatter and code blocks.
+pub fn extract_tasks(note_id: &str, content: &str) -> Vec<Task> {
+    markdown::lines(content)
+        .iter()
+        .filter_map(|line| parse_task_line(note_id, line))
+        .map(|(task, _)| task)
+        .collect()
+}
+
+/// Flip the checkbox of the task on `line` (1-based), changing only that
+/// character. Returns the new content and the updated task.
+pub fn toggle_task(note_id: &str, content: &str, line: usize) -> Result<(String, Task), String> {
+    let lines = markdown::lines(content);
+    let (mut task, state_offset) = lines
+        .iter()
+        .find(|l| l.number == line)
+        .and_then(|l| parse_task_line(note_id, l))
+        .ok_or_else(|| format!("No task on line {} of {}", line, note_id))?;
+
+    task.completed = !task.completed;
+    let state = if task.completed { "x" } else { " " };
+    let updated = markdown::apply_edits(content, vec![(state_offset, state_offset + 1, state.to_string())]);
+    Ok((updated, task))
+}
@@ -0,0 +1,147 @@
+//! Note templates stored in `.verso/templates`, with `{{variable}}` expansion.
+
+use crate::markdown::{self, LineKind};
+use crate::NoteLayout;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Folder (relative to the notes root) that templates are read from.
+pub const TEMPLATES_DIR: &str = ".verso/templates";
+
+/// Frontmatter key holding the file name template; removed from created notes.
+const FILENAME_KEY: &str = "filename";
+
+/// Variables filled in automatically.
+const BUILTIN_VARIABLES: &[&str] = &["date", "time", "datetime", "title"];
+
+static VARIABLE_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"\{\{\s*([A-Za-z_][\w-]*)\s*(?::([^}]*))?\}\}").unwrap());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateInfo {
+    /// Path relative to the templates folder, without `.md` (e.g. `meeting` or `work/standup`)
+    pub id: String,
+    pub name: String,
+    /// Custom variables used by the template (built-ins left out)
+    pub variables: Vec<String>,
+}
+
+/// A template expanded into a new note.
+pub struct ExpandedTemplate {
+    /// File name (without extension) before sanitizing, if the template sets one
+    pub file_name: Option<String>,
+    pub content: String,
+}
+
+/// All templates in the folder, sorted by ID.
+pub fn list_templates(notes_root: &Path, layout: &NoteLayout) -> Vec<TemplateInfo> {
+    let templates_root = notes_root.join(TEMPLATES_DIR);
+    let mut templates: Vec<TemplateInfo> = walkdir::WalkDir::new(&templates_root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .flatten()
+        .filter(|e| e.file_type().is_file() && layout.is_note_file(e.path()))
+        .filter_map(|e| {
+            let id = crate::id_from_abs_path(&templates_root, e.path(), layout)?;
+            let content = crate::encoding::read_to_string(e.path()).ok()?;
+            Some(TemplateInfo {
+                name: crate::extract_title_from_id(&id),
+                variables: custom_variables(&content),
+                id,
+            })
+        })
+        .collect();
+    templates.sort_by(|a, b| a.id.cmp(&b.id));
+    templates
+}
+
+/// Names of the non-built-in variables referenced in a template.
+fn custom_variables(content: &str) -> Vec<String> {
+    let names: BTreeSet<String> = VARIABLE_RE
+        .captures_iter(content)
+        .map(|caps| caps[1].to_string())
+        .filter(|name| !BUILTIN_VARIABLES.contains(&name.as_str()))
+        .collect();
+    names.into_iter().collect()
+}
+
+/// Expands template tags in `text` using the local timezone.
+/// `{{date}}`, `{{time}}` and `{{datetime}}` take an optional chrono format
+/// (`{{date:%Y%m%d}}`); other tags are looked up in `vars` and left untouched
+/// when missing.
+pub fn expand(text: &str, vars: &HashMap<String, String>, now: &DateTime<Local>) -> String {
+    VARIABLE_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            let name = &caps[1];
+            let format = caps.get(2).map(|m| m.as_str().trim()).filter(|f| !f.is_empty());
+            let default_format = match name {
+                "date" => "%Y-%m-%d",
+                "time" => "%H:%M",
+                "datetime" => "%Y-%m-%d %H:%M",
+                _ => {
+                    return vars
+                        .get(name)
+                        .cloned()
+                        .unwrap_or_else(|| caps[0].to_string());
+                }
+            };
+            format_time(now, format.unwrap_or(default_format))
+        })
+        .into_owned()
+}
+
+/// Format a timestamp, falling back to the raw format string if it is invalid.
+fn format_time(now: &DateTime<Local>, format: &str) -> String {
+    use std::fmt::Write;
+    let mut out = String::new();
+    match write!(out, "{}", now.format(format)) {
+        Ok(()) => out,
+        Err(_) => format.to_string(),
+    }
+}
+
+/// Expand a template into the file name and content of a new note. The file
+/// name comes from a `filename:` frontmatter key, which is removed from the
+/// note (along with the frontmatter if that was its only key).
+pub fn expand_template(template: &str, vars: &HashMap<String, String>, now: &DateTime<Local>) -> ExpandedTemplate {
+    let mut file_name_template = None;
+    let mut frontmatter_lines = 0;
+    let mut removed = Vec::new();
+    for line in markdown::lines(template) {
+        if line.kind != LineKind::Frontmatter {
+            break;
+        }
+        frontmatter_lines += 1;
+        if let Some((key, value)) = line.text.split_once(':') {
+            if key.trim() == FILENAME_KEY && !line.text.starts_with([' ', '\t']) {
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                file_name_template = Some(value.to_string());
+                let end = template[line.offset..]
+                    .find('\n')
+                    .map(|pos| line.offset + pos + 1)
+                    .unwrap_or(template.len());
+                removed.push((line.offset, end, String::new()));
+            }
+        }
+    }
+
+    let removed_lines = removed.len();
+    let mut body = markdown::apply_edits(template, removed);
+    // Drop frontmatter that only held the file name
+    if removed_lines > 0 && frontmatter_lines == removed_lines + 2 {
+        body = markdown::lines(&body)
+            .into_iter()
+            .find(|line| line.kind != LineKind::Frontmatter)
+            .map(|line| body[line.offset..].to_string())
+            .unwrap_or_default();
+    }
+
+    ExpandedTemplate {
+        file_name: file_name_template.map(|name| expand(&name, vars, now).trim().to_string()),
+        content: expand(&body, vars, now),
+    }
+}
@@ -92,6 +92,7 @@ import * as DropdownMenu from "@radix-ui/react-dropdown-menu";
 import { Menu, MenuItem, PredefinedMenuItem } from "@tauri-apps/api/menu";
 import { useNotes } from "../../context/NotesContext";
 import { useTheme } from "../../context/ThemeContext";
+import type { Note } from "../../types/note";
 import { Frontmatter } from "./Frontmatter";
 import { LinkEditor } from "./LinkEditor";
 import { SearchToolbar } from "./SearchToolbar";
@@ -569,18 +570,29 @@ export function Editor({
     [],
   );
 
+  // Remember a save so the note-change effect can recognise our own renames.
+  // Formatting on save can change the content, so compare against what was written.
+  const trackSave = useCallback((noteId: string, content: string) => {
+    const pending = { noteId, content };
+    lastSaveRef.current = pending;
+    return (saved: Note) => {
+      if (lastSaveRef.current === pending) {
+        lastSaveRef.current = { noteId, content: saved.content };
+      }
+    };
+  }, []);
+
   // Immediate save function (used for flushing)
   const saveImmediately = useCallback(
     async (noteId: string, content: string) => {
       setIsSaving(true);
       try {
-        lastSaveRef.current = { noteId, content };
-        await saveNote(content, noteId);
+        await saveNote(content, noteId, trackSave(noteId, content));
       } finally {
         setIsSaving(false);
       }
     },
-    [saveNote],
+    [saveNote, trackSave],
   );
 
   // Flush any pending save immediately (saves to the note currently loaded in editor)
@@ -605,7 +617,7 @@ export function Editor({
     }
 
     const savingNoteId = currentNote?.id;
-    if (!savingNoteId) return;
+    if (!savingNoteId || currentNote?.readOnly) return;
 
     needsSaveRef.current = true;
 
@@ -621,7 +633,7 @@ export function Editor({
         await saveImmediately(savingNoteId, markdown);
       }
     }, 500);
-  }, [saveImmediately, getMarkdown, currentNote?.id]);
+  }, [saveImmediately, getMarkdown, currentNote?.id, currentNote?.readOnly]);
 
   const editor = useEditor({
     extensions: [
@@ -705,7 +717,11 @@ export function Editor({
                 // Save clipboard image
                 const relativePath = await invoke<string>(
                   "save_clipboard_image",
-                  { folder, base64Data: base64 },
+                  {
+                    folder,
+                    base64Data: base64,
+                    noteId: currentNoteIdRef.current,
+                  },
                 );
 
                 // Construct absolute path using Tauri's join
@@ -878,6 +894,20 @@ export function Editor({
     };
   }, [editor]);
 
+  // Notes with bytes their encoding can't decode open read-only, since saving would lose them
+  const readOnly = currentNote?.readOnly ?? false;
+  useEffect(() => {
+    editor?.setEditable(!readOnly, false);
+  }, [editor, readOnly]);
+
+  useEffect(() => {
+    if (readOnly && currentNote) {
+      toast(t("toast.readOnlyEncoding", { encoding: currentNote.encoding }));
+    }
+    // Only once per opened note
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [readOnly, currentNote?.id]);
+
   // Load note content when the current note changes
   useEffect(() => {
     // Skip if no note or editor
@@ -1163,10 +1193,11 @@ export function Editor({
     });
     if (selected) {
       try {
-        // Copy image to assets folder and get relative path (assets/filename.ext)
+        // Copy image into the attachment folder and get its relative path
         const relativePath = await invoke<string>("copy_image_to_assets", {
           folder,
           sourcePath: selected as string,
+          noteId: currentNoteIdRef.current,
         });
 
         // Construct absolute path using Tauri's join
@@ -1374,11 +1405,10 @@ export function Editor({
         clearTimeout(sourceTimeoutRef.current);
       }
       sourceTimeoutRef.current = window.setTimeout(async () => {
-        if (currentNote) {
+        if (currentNote && !currentNote.readOnly) {
           setIsSaving(true);
           try {
-            lastSaveRef.current = { noteId: currentNote.id, content: value };
-            await saveNote(value, currentNote.id);
+            await saveNote(value, currentNote.id, trackSave(currentNote.id, value));
           } catch (error) {
             console.error("Failed to save note:", error);
             toast.error(t("toast.saveFailed"));
@@ -1388,7 +1418,7 @@ export function Editor({
         }
       }, 300);
     },
-    [currentNote, saveNote],
+    [currentNote, saveNote, trackSave],
   );
 
   // Pangu spacing: add spaces between CJK and Latin/number characters
@@ -1664,6 +1694,7 @@ export function Editor({
               value={sourceContent}
               onChange={handleSourceChange}
               dir={textDirection}
+              readOnly={currentNote.readOnly}
             />
           </div>
         ) : (
@@ -531,12 +531,14 @@ interface MarkdownSourceProps {
   value: string;
   onChange: (value: string) => void;
   dir?: string;
+  readOnly?: boolean;
 }
 
-export function MarkdownSource({ value, onChange, dir }: MarkdownSourceProps) {
+export function MarkdownSource({ value, onChange, dir, readOnly }: MarkdownSourceProps) {
   const containerRef = useRef<HTMLDivElement>(null);
   const viewRef = useRef<EditorView | null>(null);
   const dirCompartment = useRef(new Compartment());
+  const readOnlyCompartment = useRef(new Compartment());
   const onChangeRef = useRef(onChange);
   onChangeRef.current = onChange;
   const t = useT();
@@ -633,6 +635,7 @@ export function MarkdownSource({ value, onChange, dir }: MarkdownSourceProps) {
         dirCompartment.current.of(
           EditorView.editorAttributes.of({ dir: dir || "ltr" }),
         ),
+        readOnlyCompartment.current.of(EditorState.readOnly.of(!!readOnly)),
         EditorView.updateListener.of((update) => {
           if (update.docChanged) {
             onChangeRef.current(update.state.doc.toString());
@@ -681,6 +684,15 @@ export function MarkdownSource({ value, onChange, dir }: MarkdownSourceProps) {
     });
   }, [dir]);
 
+  // Sync read-only state
+  useEffect(() => {
+    const view = viewRef.current;
+    if (!view) return;
+    view.dispatch({
+      effects: readOnlyCompartment.current.reconfigure(EditorState.readOnly.of(!!readOnly)),
+    });
+  }, [readOnly]);
+
   return (
     <div
       ref={containerRef}
@@ -33,7 +33,7 @@ interface NotesDataContextValue {
 interface NotesActionsContextValue {
   selectNote: (id: string) => Promise<void>;
   createNote: () => Promise<void>;
-  saveNote: (content: string, noteId?: string) => Promise<void>;
+  saveNote: (content: string, noteId?: string, onSaved?: (note: Note) => void) => Promise<void>;
   deleteNote: (id: string) => Promise<void>;
   duplicateNote: (id: string) => Promise<void>;
   refreshNotes: () => Promise<void>;
@@ -144,7 +144,7 @@ export function NotesProvider({ folder, initialSelectId, children }: NotesProvid
   }, [folder, refreshNotes]);
 
   const saveNote = useCallback(
-    async (content: string, noteId?: string) => {
+    async (content: string, noteId?: string, onSaved?: (note: Note) => void) => {
       // Use provided noteId (for flush saves) or fall back to currentNote.id
       const savingNoteId = noteId || currentNote?.id;
       if (!savingNoteId) return;
@@ -156,6 +156,13 @@ export function NotesProvider({ folder, initialSelectId, children }: NotesProvid
 
         const updated = await notesService.saveNote(folder, savingNoteId, content);
         updatedId = updated.id;
+        // Before the state updates below, so the caller sees the saved content
+        // (which differs from what was sent when formatting on save)
+        onSaved?.(updated);
+
+        if (updated.linkRewriteErrors?.length) {
+          setError(`Some links to the renamed note were not updated: ${updated.linkRewriteErrors.join("; ")}`);
+        }
 
         // If the note was renamed (ID changed), also mark the new ID
         if (updated.id !== savingNoteId) {
@@ -273,6 +273,7 @@ const en = {
   "toast.markdownSaveFailed": "Failed to save markdown",
   "toast.deleteFailed": "Failed to delete note",
   "toast.saveFailed": "Failed to save note",
+  "toast.readOnlyEncoding": "This note has bytes that aren't valid {encoding}, so it's open read-only to keep the file intact",
   "toast.editorNotAvailable": "Editor not available",
   "toast.fontSize": "Font size: {size}px",
   "toast.fontSizeReset": "Font size reset to default",
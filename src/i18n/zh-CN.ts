@@ -275,6 +275,7 @@ const zhCN: Translations = {
   "toast.markdownSaveFailed": "\u4fdd\u5b58 Markdown \u5931\u8d25",
   "toast.deleteFailed": "\u5220\u9664\u6587\u6863\u5931\u8d25",
   "toast.saveFailed": "\u4fdd\u5b58\u6587\u6863\u5931\u8d25",
+  "toast.readOnlyEncoding": "\u6b64\u6587\u6863\u5305\u542b\u65e0\u6cd5\u6309 {encoding} \u89e3\u7801\u7684\u5b57\u8282\uff0c\u5df2\u4ee5\u53ea\u8bfb\u65b9\u5f0f\u6253\u5f00\u4ee5\u4fdd\u62a4\u6587\u4ef6",
   "toast.editorNotAvailable": "\u7f16\u8f91\u5668\u4e0d\u53ef\u7528",
   "toast.fontSize": "\u5b57\u53f7\uff1a{size}px",
   "toast.fontSizeReset": "\u5b57\u53f7\u5df2\u6062\u590d\u9ed8\u8ba4",
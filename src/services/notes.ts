@@ -17,8 +17,13 @@ export async function readNote(folder: string, id: string): Promise<Note> {
   return invoke("read_note", { folder, id });
 }
 
-export async function saveNote(folder: string, id: string | null, content: string): Promise<Note> {
-  return invoke("save_note", { folder, id, content });
+export async function saveNote(
+  folder: string,
+  id: string | null,
+  content: string,
+  convertToUtf8?: boolean
+): Promise<Note> {
+  return invoke("save_note", { folder, id, content, convertToUtf8 });
 }
 
 export async function deleteNote(folder: string, id: string): Promise<void> {
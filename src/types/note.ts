@@ -11,6 +11,11 @@ export interface Note {
   content: string;
   path: string;
   modified: number;
+  encoding: string; // WHATWG name, e.g. "UTF-8", "GBK", "UTF-16LE"
+  bom: boolean;
+  lineEnding: LineEnding;
+  linkRewriteErrors?: string[]; // notes whose links couldn't follow a rename
+  readOnly: boolean; // bytes invalid in its encoding; saving would lose them
 }
 
 export interface ThemeSettings {
@@ -30,6 +35,48 @@ export interface EditorFontSettings {
 
 export type LocaleSetting = "auto" | "en" | "zh-CN";
 
+export type AttachmentLocation =
+  | "vault" // assets/ at the root of the notes folder
+  | "noteSubfolder" // assets/<note>/
+  | "noteFolder" // next to the note
+  | "custom"; // customPath, relative to the notes folder
+
+export interface AttachmentSettings {
+  location: AttachmentLocation;
+  customPath?: string;
+  // Tokens: {{date}} {{time}} {{timestamp}} {{title}} {{name}} {{counter}}
+  nameTemplate?: string;
+}
+
+export interface DailyNoteSettings {
+  format?: string; // chrono format without "/", default "%Y-%m-%d"
+  template?: string; // template ID from .verso/templates
+}
+
+export type FilenameStrategy =
+  | "titleSynced" // renamed whenever the title changes
+  | "titleFrozen" // named from the first title, then kept
+  | "timestamp" // YYYYMMDDHHMMSS ID on creation
+  | "slug"; // lowercase ASCII slug of the title
+
+export type LineEnding = "lf" | "crlf";
+
+// Settings that only apply to one notes folder
+export interface FolderSettings {
+  filenameStrategy?: FilenameStrategy;
+  lineEnding?: LineEnding; // forced on save; unset keeps each file's own
+}
+
+export interface FormatOptions {
+  bullet?: "-" | "*" | "+";
+  emphasis?: "*" | "_";
+  strong?: "*" | "_"; // written twice
+  atxHeadings?: boolean; // rewrite underlined headings as # headings
+  alignTables?: boolean;
+  lineWidth?: number; // reflow paragraphs; unset keeps line breaks
+  normalizeBlankLines?: boolean;
+}
+
 // App settings (stored in app data directory)
 export interface Settings {
   theme: ThemeSettings;
@@ -37,4 +84,12 @@ export interface Settings {
   textDirection?: TextDirection;
   editorWidth?: EditorWidth;
   language?: LocaleSetting;
+  imageSizeLimit?: number; // bytes; larger pasted images are re-encoded
+  attachments?: AttachmentSettings;
+  attachmentSizeLimit?: number; // bytes; larger imported files are rejected
+  dailyNotes?: DailyNoteSettings;
+  noteExtensions?: string[]; // extra note extensions besides md/markdown, e.g. ["mdx", "txt"]
+  formatOnSave?: boolean;
+  formatOptions?: FormatOptions;
+  folders?: Record<string, FolderSettings>; // keyed by folder path
 }